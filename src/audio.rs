@@ -0,0 +1,172 @@
+use crate::models::Player;
+use crate::preferences::*;
+use crate::settings::Settings;
+use crate::utils::*;
+use macroquad::audio::{load_sound_from_bytes, play_sound, PlaySoundParams, Sound};
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Fire,
+    Impact,
+    Hit,
+    Death,
+    Footstep,
+}
+impl SoundEffect {
+    const ALL: [SoundEffect; 5] = [
+        SoundEffect::Fire,
+        SoundEffect::Impact,
+        SoundEffect::Hit,
+        SoundEffect::Death,
+        SoundEffect::Footstep,
+    ];
+    //mono samples at SOUND_SAMPLE_RATE, the effects are synthesized so the game needs no sound files
+    fn samples(&self) -> Vec<f32> {
+        let rate = SOUND_SAMPLE_RATE as f32;
+        let samples = |duration: f32, sample: &dyn Fn(f32) -> f32| -> Vec<f32> {
+            (0..(duration * rate) as usize)
+                .map(|index| sample(index as f32 / rate))
+                .collect()
+        };
+        let noise = || rand::gen_range(-1.0, 1.0);
+        match self {
+            SoundEffect::Fire => samples(0.2, &|t| {
+                (0.7 * noise() + 0.3 * (TAU * 110.0 * t).sin()) * (-t * 25.0).exp()
+            }),
+            SoundEffect::Impact => samples(0.06, &|t| noise() * (-t * 80.0).exp()),
+            SoundEffect::Hit => samples(0.1, &|t| (TAU * 880.0 * t).sin() * (1.0 - t / 0.1)),
+            //falling tone
+            SoundEffect::Death => samples(0.7, &|t| {
+                (TAU * (440.0 * t - 230.0 * t * t)).sin() * (1.0 - t / 0.7)
+            }),
+            SoundEffect::Footstep => samples(0.08, &|t| {
+                (0.6 * (TAU * 70.0 * t).sin() + 0.4 * noise()) * (-t * 50.0).exp()
+            }),
+        }
+    }
+    //effects of the player itself are louder than the same effect of somebody else
+    fn volume(&self) -> f32 {
+        match self {
+            SoundEffect::Fire => 0.6,
+            SoundEffect::Impact => 0.4,
+            SoundEffect::Hit => 0.5,
+            SoundEffect::Death => 0.7,
+            SoundEffect::Footstep => 0.25,
+        }
+    }
+}
+
+/*
+    sound effects with positional playback.
+    macroquad can not pan a playing sound, so every effect is loaded once for each of SOUND_PAN_STEPS
+    stereo positions and the closest one is played
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Audio {
+    sounds: HashMap<SoundEffect, Vec<Sound>>,
+    //distance walked since the last footstep, by player id
+    steps: HashMap<String, (Vec2, f32)>,
+    loaded: bool,
+}
+impl Audio {
+    //without the audio feature nothing is loaded, so nothing is ever played
+    pub async fn load() -> Self {
+        let mut sounds = HashMap::new();
+        if !cfg!(feature = "audio") {
+            return Audio {
+                sounds,
+                steps: HashMap::new(),
+                loaded: true,
+            };
+        }
+        for effect in SoundEffect::ALL {
+            let samples = effect.samples();
+            let mut panned = vec![];
+            for step in 0..SOUND_PAN_STEPS {
+                let pan = step as f32 / (SOUND_PAN_STEPS - 1) as f32 * 2.0 - 1.0;
+                let bytes = stereo_wav(&samples, SOUND_SAMPLE_RATE, pan);
+                match load_sound_from_bytes(&bytes).await {
+                    Ok(sound) => panned.push(sound),
+                    Err(e) => println!("Error while loading sound {:?}: {:?}", effect, e),
+                }
+            }
+            sounds.insert(effect, panned);
+        }
+        Audio {
+            sounds,
+            steps: HashMap::new(),
+            loaded: true,
+        }
+    }
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+    //pan from -1 (left) to 1 (right)
+    pub fn play(&self, effect: SoundEffect, volume: f32, pan: f32, settings: &Settings) {
+        if settings.mute || settings.volume <= 0.0 || volume <= 0.0 {
+            return;
+        }
+        let Some(panned) = self.sounds.get(&effect) else {
+            return;
+        };
+        let step = ((pan.clamp(-1.0, 1.0) + 1.0) / 2.0 * (panned.len() as f32 - 1.0)).round();
+        if let Some(sound) = panned.get(step as usize) {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: false,
+                    volume: effect.volume() * volume * settings.volume,
+                },
+            );
+        }
+    }
+    //sound of something happening at position, heard by the player
+    pub fn play_at(
+        &self,
+        effect: SoundEffect,
+        position: Vec3,
+        player: &Player,
+        settings: &Settings,
+    ) {
+        let (volume, pan) = sound_volume_and_pan(player.position_vec3, player.right, position);
+        self.play(effect, volume, pan, settings);
+    }
+    //a footstep every FOOTSTEP_DISTANCE walked, for the player and everybody else
+    pub fn footsteps(&mut self, players: &[&Player], player: &Player, settings: &Settings) {
+        for _player in players {
+            let position = vec2(_player.position.x, _player.position.z);
+            let stepped = match self.steps.get_mut(&_player.id) {
+                Some((last, walked)) => {
+                    //respawns are jumps, not steps
+                    let distance = last.distance(position);
+                    if distance < FOOTSTEP_MAX_JUMP {
+                        *walked += distance;
+                    }
+                    *last = position;
+                    let stepped = *walked >= FOOTSTEP_DISTANCE;
+                    if stepped {
+                        *walked = 0.0;
+                    }
+                    stepped
+                }
+                None => {
+                    self.steps.insert(_player.id.clone(), (position, 0.0));
+                    false
+                }
+            };
+            if stepped {
+                let footstep_position = vec3(position.x, 0.5, position.y);
+                if _player.id == player.id {
+                    self.play(SoundEffect::Footstep, 1.0, 0.0, settings);
+                } else {
+                    self.play_at(SoundEffect::Footstep, footstep_position, player, settings);
+                }
+            }
+        }
+        self.steps
+            .retain(|id, _| players.iter().any(|_player| _player.id == *id));
+    }
+}
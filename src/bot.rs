@@ -0,0 +1,76 @@
+use crate::input::Controls;
+use crate::models::*;
+use crate::preferences::*;
+use crate::utils::*;
+use macroquad::prelude::*;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+//computer controlled player, used for scripted multi-client runs and automated tests
+#[derive(Debug, Clone, Default)]
+pub struct Bot {
+    last_position: Option<Vec3>,
+    //radians still to turn before walking on
+    turn_left: f32,
+    fire_cooldown: f32,
+}
+impl Bot {
+    pub fn controls(
+        &mut self,
+        player: &Player,
+        enemies: &[Player],
+        hittables: &[Hittable],
+        delta: f32,
+    ) -> Controls {
+        let mut controls = Controls::default();
+        let max_turn = BOT_TURN_SPEED * delta;
+        self.fire_cooldown -= delta;
+        //keep the view level
+        controls.look.y = -player.pitch;
+
+        //turn towards the closest visible enemy and shoot once aimed
+        let eye = vec3(player.position.x, PLAYER_HEIGHT, player.position.z);
+        let target = enemies
+            .iter()
+            .filter(|enemy| matches!(enemy.player_status, PlayerStatus::Active))
+            .map(|enemy| vec3(enemy.position.x, PLAYER_HEIGHT, enemy.position.z))
+            .filter(|position| is_in_line_of_sight(eye, *position, hittables))
+            .min_by(|a, b| (*a - eye).length().total_cmp(&(*b - eye).length()));
+        if let Some(position) = target {
+            let to_enemy = position - eye;
+            let angle = wrap_angle(to_enemy.z.atan2(to_enemy.x) - player.yaw);
+            controls.look.x = angle.clamp(-max_turn, max_turn);
+            if angle.abs() < BOT_AIM_TOLERANCE && self.fire_cooldown <= 0.0 {
+                controls.fire = true;
+                self.fire_cooldown = BOT_FIRE_INTERVAL;
+            }
+            self.last_position = None;
+            return controls;
+        }
+
+        //wander around, turn when a wall is in the way
+        if self.turn_left != 0.0 {
+            let step = self.turn_left.clamp(-max_turn, max_turn);
+            controls.look.x = step;
+            self.turn_left -= step;
+            self.last_position = None;
+            return controls;
+        }
+        controls.move_forward = true;
+        if let Some(last_position) = self.last_position {
+            if (player.position_vec3 - last_position).length() < BOT_STUCK_DISTANCE {
+                self.turn_left = if generate_up_to(2) == 0 {
+                    FRAC_PI_2
+                } else {
+                    -FRAC_PI_2
+                };
+            }
+        }
+        self.last_position = Some(player.position_vec3);
+        controls
+    }
+}
+
+//angle in range -PI..PI
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
@@ -0,0 +1,169 @@
+use crate::layout::Layout;
+use crate::models::*;
+use crate::preferences::*;
+use macroquad::miniquad::date;
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct ChatLine {
+    pub name: String,
+    pub text: String,
+    //seconds since the epoch when the line was received, lines fade out after CHAT_LINE_DURATION.
+    //get_time can not be used, lines are pushed from the listener thread
+    pub time: f64,
+}
+
+//received lines, filled by the server listener
+#[derive(Debug, Clone, Default)]
+pub struct ChatLog {
+    lines: VecDeque<ChatLine>,
+}
+impl ChatLog {
+    pub fn push(&mut self, name: &str, text: &str) {
+        self.lines.push_back(ChatLine {
+            name: String::from(name),
+            text: text.chars().take(MAX_CHAT_LENGTH).collect(),
+            time: date::now(),
+        });
+        while self.lines.len() > CHAT_LOG_LENGTH {
+            self.lines.pop_front();
+        }
+    }
+}
+
+//text entry and log overlay of the in-game chat
+#[derive(Debug, Clone, Default)]
+pub struct Chat {
+    pub log: Arc<Mutex<ChatLog>>,
+    input: Option<String>,
+    //lines scrolled back from the newest while typing
+    scroll: usize,
+    sent: VecDeque<f64>,
+}
+impl Chat {
+    pub fn is_typing(&self) -> bool {
+        self.input.is_some()
+    }
+    pub fn open(&mut self) {
+        //drop the character of the key that opened the chat
+        while get_char_pressed().is_some() {}
+        self.input = Some(String::new());
+        self.scroll = 0;
+    }
+    //returns the text to send once Enter is pressed
+    pub fn update(&mut self) -> Option<String> {
+        let input = self.input.as_mut()?;
+        if is_key_pressed(KeyCode::Escape) {
+            self.input = None;
+            return None;
+        }
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && input.chars().count() < MAX_CHAT_LENGTH {
+                input.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            input.pop();
+        }
+        let log_length = self.log.lock().map(|log| log.lines.len()).unwrap_or(0);
+        if is_key_pressed(KeyCode::PageUp) {
+            self.scroll = usize::min(self.scroll + 1, log_length.saturating_sub(1));
+        }
+        if is_key_pressed(KeyCode::PageDown) {
+            self.scroll = self.scroll.saturating_sub(1);
+        }
+        if !is_key_pressed(KeyCode::Enter) {
+            return None;
+        }
+        let text = String::from(input.trim());
+        self.input = None;
+        if text.is_empty() {
+            return None;
+        }
+        let now = date::now();
+        while self
+            .sent
+            .front()
+            .is_some_and(|time| now - time > CHAT_RATE_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= CHAT_RATE_LIMIT {
+            if let Ok(mut log) = self.log.lock() {
+                log.push("", "you are sending messages too fast");
+            }
+            return None;
+        }
+        self.sent.push_back(now);
+        Some(text)
+    }
+    pub fn draw(&self, font: &Font, layout: &Layout) {
+        let Ok(log) = self.log.lock() else {
+            return;
+        };
+        let now = date::now();
+        //older lines are only shown while typing
+        let lines: Vec<&ChatLine> = log
+            .lines
+            .iter()
+            .rev()
+            .skip(self.scroll)
+            .filter(|line| self.is_typing() || now - line.time < CHAT_LINE_DURATION)
+            .take(CHAT_VISIBLE_LINES)
+            .collect();
+        if lines.is_empty() && !self.is_typing() {
+            return;
+        }
+        draw_rectangle(
+            layout.chat.x,
+            layout.chat.y,
+            layout.chat.w,
+            layout.chat.h,
+            Color::new(0.0, 0.0, 0.0, 0.4),
+        );
+        let params = TextParams {
+            font: Some(font),
+            font_size: GAME_FONT_SIZE,
+            font_scale: layout.scale,
+            font_scale_aspect: 1.0,
+            rotation: 0.0,
+            color: WHITE,
+        };
+        let line_height = layout.chat.h / (CHAT_VISIBLE_LINES + 1) as f32;
+        let left = layout.chat.x + 4.0 * layout.scale;
+        let mut top_offset = layout.chat.y + layout.chat.h - line_height;
+        for line in lines {
+            let text = if line.name.is_empty() {
+                line.text.clone()
+            } else {
+                format!("{}: {}", line.name, line.text)
+            };
+            draw_text_ex(text.as_str(), left, top_offset, params.clone());
+            top_offset -= line_height;
+        }
+        if let Some(ref input) = self.input {
+            draw_text_ex(
+                format!("> {}_", input).as_str(),
+                left,
+                layout.chat.y + layout.chat.h - 4.0 * layout.scale,
+                TextParams {
+                    color: YELLOW,
+                    ..params
+                },
+            );
+        }
+    }
+}
+
+//received chat message, lines sent by this player are already in the log
+pub fn receive_chat(log: &Arc<Mutex<ChatLog>>, message: &ChatMessage, player_id: &str) {
+    if message.sender_id == player_id {
+        return;
+    }
+    match log.lock() {
+        Ok(mut log) => log.push(&message.name, &message.text),
+        Err(e) => println!("Error while locking chat log: {:?}", e),
+    }
+}
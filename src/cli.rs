@@ -0,0 +1,145 @@
+use crate::models::Status;
+use crate::preferences::*;
+use crate::utils::*;
+use std::path::Path;
+
+pub const USAGE: &str = "Usage: maze_wars_client [OPTIONS]
+
+Options:
+  --server <ADDRESS>   server to join, skips the address prompt
+  --name <NAME>        player name, together with --server joins right away
+  --map <PATH>         map file to play on
+  --settings <PATH>    settings file to use instead of settings.json
+  --bot                let the computer control the player
+  --spectate           join without taking part in the match
+  --help               print this message";
+
+//command line arguments, every option can also be written as --option=value
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub server: Option<String>,
+    pub name: Option<String>,
+    pub map: Option<String>,
+    pub settings: Option<String>,
+    pub bot: bool,
+    pub spectate: bool,
+    pub help: bool,
+}
+impl Args {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) => (String::from(option), Some(String::from(value))),
+                None => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String, String> {
+                match inline_value.clone() {
+                    Some(value) => Ok(value),
+                    None => args.next().ok_or(format!("missing value for `{}`", option)),
+                }
+            };
+            match option.as_str() {
+                "--server" => parsed.server = Some(value()?),
+                "--name" => parsed.name = Some(value()?),
+                "--map" => parsed.map = Some(value()?),
+                "--settings" => parsed.settings = Some(value()?),
+                "--bot" => parsed.bot = true,
+                "--spectate" => parsed.spectate = true,
+                "--help" | "-h" => parsed.help = true,
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+        parsed.validate()?;
+        Ok(parsed)
+    }
+    fn validate(&self) -> Result<(), String> {
+        if let Some(ref server) = self.server {
+            if !server.chars().all(is_valid_address_char) {
+                return Err(format!("invalid server address `{}`", server));
+            }
+            if let Err(e) = split_host_port(server) {
+                return Err(format!("invalid server address `{}`: {}", server, e));
+            }
+        }
+        if let Some(ref name) = self.name {
+            if name.len() < 3 || name.len() > MAX_NAME_LENGTH {
+                return Err(format!(
+                    "name must be between 3 and {} characters long",
+                    MAX_NAME_LENGTH
+                ));
+            }
+            if !name.chars().all(is_valid_name_char) {
+                return Err(format!("invalid characters in name `{}`", name));
+            }
+        }
+        if let Some(ref map) = self.map {
+            if !Path::new(map).exists() {
+                return Err(format!("map file `{}` does not exist", map));
+            }
+        }
+        if self.bot && self.spectate {
+            return Err(String::from(
+                "--bot and --spectate can not be used together",
+            ));
+        }
+        Ok(())
+    }
+    //first screen to show, everything that was given on the command line is skipped
+    pub fn start_status(&self) -> Status {
+        match (&self.server, &self.name) {
+            (Some(_), Some(_)) => Status::Init,
+            (Some(_), None) => Status::EnterName,
+            _ => Status::EnterIP,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn options_with_separate_and_inline_values() {
+        let args = parse(&[
+            "--server",
+            "127.0.0.1:4000",
+            "--name=alice",
+            "--map=maps/map_one.txt",
+        ])
+        .unwrap();
+        assert_eq!(args.server.as_deref(), Some("127.0.0.1:4000"));
+        assert_eq!(args.name.as_deref(), Some("alice"));
+        assert_eq!(args.map.as_deref(), Some("maps/map_one.txt"));
+        assert!(!args.bot && !args.spectate && !args.help);
+    }
+
+    #[test]
+    fn unknown_and_incomplete_options_are_errors() {
+        assert!(parse(&["--fast"]).is_err());
+        assert!(parse(&["alice"]).is_err());
+        assert!(parse(&["--name"]).is_err());
+        assert!(parse(&["--bot", "--spectate"]).is_err());
+    }
+
+    #[test]
+    fn help_flag() {
+        assert!(parse(&["--help"]).unwrap().help);
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(!parse(&[]).unwrap().help);
+    }
+
+    #[test]
+    fn start_status_skips_given_prompts() {
+        assert_eq!(parse(&[]).unwrap().start_status(), Status::EnterIP);
+        let server = parse(&["--server=127.0.0.1:4000"]).unwrap();
+        assert_eq!(server.start_status(), Status::EnterName);
+        let both = parse(&["--server=127.0.0.1:4000", "--name=alice"]).unwrap();
+        assert_eq!(both.start_status(), Status::Init);
+    }
+}
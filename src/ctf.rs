@@ -0,0 +1,251 @@
+use crate::models::*;
+use crate::preferences::*;
+use macroquad::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlagLocation {
+    Base,
+    Dropped,
+    //id of the carrier
+    Carried(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Flag {
+    pub team: Team,
+    pub base: Vec2,
+    pub position: Vec2,
+    pub location: FlagLocation,
+}
+
+/*
+    flags as seen by this client, from the carrier and drop state every player reports.
+    When two players claim the same flag the one with the smaller id carries it
+*/
+pub fn flags(bases: &[(Team, Vec2)], players: &[&Player]) -> Vec<Flag> {
+    let mut flags = vec![];
+    for (team, base) in bases {
+        let carrier = players
+            .iter()
+            .filter(|player| {
+                player.carrying_flag == Some(*team)
+                    && matches!(player.player_status, PlayerStatus::Active)
+            })
+            .min_by(|a, b| a.id.cmp(&b.id));
+        let dropped = players.iter().find_map(|player| {
+            player
+                .dropped_flag
+                .as_ref()
+                .filter(|dropped| dropped.team == *team)
+        });
+        let (position, location) = match (carrier, dropped) {
+            (Some(carrier), _) => (
+                vec2(carrier.position.x, carrier.position.z),
+                FlagLocation::Carried(carrier.id.clone()),
+            ),
+            (None, Some(dropped)) => (
+                vec2(dropped.position.x, dropped.position.z),
+                FlagLocation::Dropped,
+            ),
+            (None, None) => (*base, FlagLocation::Base),
+        };
+        flags.push(Flag {
+            team: *team,
+            base: *base,
+            position,
+            location,
+        });
+    }
+    flags
+}
+
+fn is_close(player: &Player, target: Vec2) -> bool {
+    vec2(player.position_vec3.x, player.position_vec3.z).distance(target) < FLAG_PICKUP_RADIUS
+}
+
+//pickup, capture and drop for the local player, returns true when the change has to be sent
+pub fn update_flags(player: &mut Player, flags: &[Flag], now: f64) -> bool {
+    let mut changed = false;
+    let flag_of = |team: Team| flags.iter().find(|flag| flag.team == team);
+
+    //a dropped flag goes back once it is picked up or after a while
+    if let Some(ref dropped) = player.dropped_flag {
+        let picked_up = flag_of(dropped.team)
+            .is_some_and(|flag| matches!(flag.location, FlagLocation::Carried(_)));
+        if picked_up || now - player.flag_dropped_at > FLAG_RETURN_DELAY {
+            player.dropped_flag = None;
+            changed = true;
+        }
+    }
+    if player.death_time.is_some() {
+        if let Some(team) = player.carrying_flag.take() {
+            player.dropped_flag = Some(DroppedFlag {
+                team,
+                position: player.position.clone(),
+            });
+            player.flag_dropped_at = now;
+            changed = true;
+        }
+        return changed;
+    }
+    let Some(own_team) = player.team else {
+        return changed;
+    };
+    if player.spectator {
+        return changed;
+    }
+
+    match player.carrying_flag {
+        Some(carried) => {
+            let lost = flag_of(carried).is_some_and(
+                |flag| matches!(flag.location, FlagLocation::Carried(ref id) if *id != player.id),
+            );
+            //scoring needs the own flag at home
+            let captured = flag_of(own_team).is_some_and(|flag| {
+                flag.location == FlagLocation::Base && is_close(player, flag.base)
+            });
+            if lost {
+                player.carrying_flag = None;
+                changed = true;
+            } else if captured {
+                player.carrying_flag = None;
+                player.captures += 1;
+                player.score += FLAG_CAPTURE_SCORE;
+                changed = true;
+            }
+        }
+        None => {
+            if let Some(flag) = flags.iter().find(|flag| {
+                flag.team != own_team
+                    && !matches!(flag.location, FlagLocation::Carried(_))
+                    && is_close(player, flag.position)
+            }) {
+                player.carrying_flag = Some(flag.team);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+//pole and cloth at the flag position, carried flags are drawn above the carrier
+pub fn draw_flags(flags: &[Flag], player_id: &str) {
+    for flag in flags {
+        let color = flag.team.color();
+        //base marker on the floor
+        draw_cube(
+            vec3(flag.base.x, 0.51, flag.base.y),
+            vec3(0.5, 0.01, 0.5),
+            None,
+            Color { a: 0.5, ..color },
+        );
+        let height = match flag.location {
+            FlagLocation::Carried(ref id) if id == player_id => continue,
+            FlagLocation::Carried(_) => 1.15,
+            _ => 0.8,
+        };
+        draw_cube(
+            vec3(flag.position.x, height, flag.position.y),
+            vec3(0.02, 0.6, 0.02),
+            None,
+            DARKGRAY,
+        );
+        draw_cube(
+            vec3(flag.position.x + 0.07, height + 0.22, flag.position.y),
+            vec3(0.14, 0.1, 0.01),
+            None,
+            color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASES: [(Team, Vec2); 2] = [(Team::Red, vec2(1.0, 1.0)), (Team::Blue, vec2(9.0, 9.0))];
+
+    fn player(id: &str, team: Team, x: f32, z: f32) -> Player {
+        let mut player = Player::new();
+        player.id = String::from(id);
+        player.team = Some(team);
+        player.position = Position::build(x, z);
+        player.position_vec3 = vec3(x, PLAYER_HEIGHT, z);
+        player
+    }
+
+    fn flag(flags: &[Flag], team: Team) -> &Flag {
+        flags.iter().find(|flag| flag.team == team).unwrap()
+    }
+
+    #[test]
+    fn enemy_flag_is_picked_up_at_its_base() {
+        let mut red = player("a", Team::Red, 9.0, 9.1);
+        let seen = flags(&BASES, &[&red]);
+        assert!(update_flags(&mut red, &seen, 0.0));
+        assert_eq!(red.carrying_flag, Some(Team::Blue));
+        assert_eq!(
+            flag(&flags(&BASES, &[&red]), Team::Blue).location,
+            FlagLocation::Carried(String::from("a"))
+        );
+
+        //the own flag stays at home
+        let mut blue = player("b", Team::Blue, 9.0, 9.0);
+        assert!(!update_flags(&mut blue, &seen, 0.0));
+        assert_eq!(blue.carrying_flag, None);
+    }
+
+    #[test]
+    fn smaller_id_carries_a_contested_flag() {
+        let mut first = player("a", Team::Red, 9.0, 9.0);
+        let mut second = player("b", Team::Red, 9.0, 9.0);
+        first.carrying_flag = Some(Team::Blue);
+        second.carrying_flag = Some(Team::Blue);
+        let seen = flags(&BASES, &[&second, &first]);
+        assert!(update_flags(&mut second, &seen, 0.0));
+        assert_eq!(second.carrying_flag, None);
+        assert!(!update_flags(&mut first, &seen, 0.0));
+        assert_eq!(first.carrying_flag, Some(Team::Blue));
+    }
+
+    #[test]
+    fn capture_needs_the_own_flag_at_home() {
+        let mut red = player("a", Team::Red, 1.0, 1.0);
+        red.carrying_flag = Some(Team::Blue);
+        let mut blue = player("b", Team::Blue, 5.0, 5.0);
+        blue.carrying_flag = Some(Team::Red);
+        let seen = flags(&BASES, &[&red, &blue]);
+        assert!(!update_flags(&mut red, &seen, 0.0));
+        assert_eq!(red.captures, 0);
+
+        blue.carrying_flag = None;
+        let seen = flags(&BASES, &[&red, &blue]);
+        assert!(update_flags(&mut red, &seen, 0.0));
+        assert_eq!(red.captures, 1);
+        assert_eq!(red.score, FLAG_CAPTURE_SCORE);
+        assert_eq!(red.carrying_flag, None);
+    }
+
+    #[test]
+    fn flag_is_dropped_on_death_and_returns_after_a_while() {
+        let mut red = player("a", Team::Red, 5.0, 6.0);
+        red.carrying_flag = Some(Team::Blue);
+        red.death_time = Some(10.0);
+        let seen = flags(&BASES, &[&red]);
+        assert!(update_flags(&mut red, &seen, 10.0));
+        assert_eq!(red.carrying_flag, None);
+        let blue_flag = flag(&flags(&BASES, &[&red]), Team::Blue).clone();
+        assert_eq!(blue_flag.location, FlagLocation::Dropped);
+        assert_eq!(blue_flag.position, vec2(5.0, 6.0));
+
+        let seen = flags(&BASES, &[&red]);
+        assert!(!update_flags(&mut red, &seen, 10.0 + FLAG_RETURN_DELAY));
+        assert!(red.dropped_flag.is_some());
+        assert!(update_flags(&mut red, &seen, 10.5 + FLAG_RETURN_DELAY));
+        assert!(red.dropped_flag.is_none());
+        assert_eq!(
+            flag(&flags(&BASES, &[&red]), Team::Blue).location,
+            FlagLocation::Base
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use crate::models::*;
+use crate::preferences::*;
+use crate::utils::*;
+use macroquad::prelude::*;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerState {
+    Querying,
+    //ping is the round trip of the status query in milliseconds
+    Online { info: ServerInfo, ping: u128 },
+    Unreachable(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct BrowsedServer {
+    //as typed or remembered, used to join the server
+    pub address: String,
+    pub socket_address: Option<SocketAddr>,
+    pub state: ServerState,
+}
+
+type ServerList = Arc<Mutex<Vec<BrowsedServer>>>;
+
+fn query_socket(socket: &UdpSocket, target: SocketAddr) -> Result<(), String> {
+    socket
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .map_err(|e| format!("could not set socket timeout: {}", e))?;
+    let query = serde_json::to_string(&StatusQuery::new())
+        .map_err(|e| format!("could not create query: {}", e))?;
+    socket
+        .send_to(query.as_bytes(), target)
+        .map_err(|e| format!("could not send query: {}", e))?;
+    Ok(())
+}
+
+//waits for answers until DISCOVERY_TIMEOUT, accept decides which senders are expected
+fn receive_answers(
+    socket: &UdpSocket,
+    sent_at: Instant,
+    mut accept: impl FnMut(SocketAddr, ServerInfo, u128) -> bool,
+) {
+    let mut buffer = [0; 2048];
+    while sent_at.elapsed().as_secs_f64() < DISCOVERY_TIMEOUT {
+        let Ok((size, address)) = socket.recv_from(&mut buffer) else {
+            continue;
+        };
+        let ping = sent_at.elapsed().as_millis();
+        match serde_json::from_slice::<ServerInfo>(&buffer[..size]) {
+            Ok(info) => {
+                if !accept(address, info, ping) {
+                    return;
+                }
+            }
+            Err(e) => println!("Invalid status answer from {}: {}", address, e),
+        }
+    }
+}
+
+//broadcasts a query on the local network, every answering server is added to the list
+fn discover(servers: ServerList) -> Result<(), String> {
+    let socket =
+        UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("could not open socket: {}", e))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("broadcast is not available: {}", e))?;
+    let sent_at = Instant::now();
+    query_socket(
+        &socket,
+        SocketAddr::from(([255, 255, 255, 255], DEFAULT_SERVER_PORT)),
+    )?;
+    thread::spawn(move || {
+        receive_answers(&socket, sent_at, |address, info, ping| {
+            let mut servers = match servers.lock() {
+                Ok(servers) => servers,
+                Err(e) => {
+                    println!("Error while locking servers: {:?}", e);
+                    return true;
+                }
+            };
+            let state = ServerState::Online { info, ping };
+            match servers
+                .iter_mut()
+                .find(|server| server.socket_address == Some(address))
+            {
+                //known servers answer their own query, the broadcast answer only fills the gap
+                Some(server) => {
+                    if server.state == ServerState::Querying {
+                        server.state = state;
+                    }
+                }
+                None => servers.push(BrowsedServer {
+                    address: address.to_string(),
+                    socket_address: Some(address),
+                    state,
+                }),
+            }
+            true
+        });
+    });
+    Ok(())
+}
+
+//resolves and queries one known server, in a thread so host name lookups do not block rendering
+fn query_known(servers: ServerList, address: String) {
+    thread::spawn(move || {
+        let set_state = |socket_address: Option<SocketAddr>, state: ServerState| {
+            let mut servers = match servers.lock() {
+                Ok(servers) => servers,
+                Err(e) => {
+                    println!("Error while locking servers: {:?}", e);
+                    return;
+                }
+            };
+            //drop a copy found by the broadcast before the host name was resolved
+            if socket_address.is_some() {
+                servers.retain(|server| {
+                    server.address == address || server.socket_address != socket_address
+                });
+            }
+            if let Some(server) = servers.iter_mut().find(|server| server.address == address) {
+                server.socket_address = socket_address;
+                server.state = state;
+            }
+        };
+        let (target, socket) = match connect(&address) {
+            Ok(connection) => connection,
+            Err(e) => return set_state(None, ServerState::Unreachable(e)),
+        };
+        let sent_at = Instant::now();
+        if let Err(e) = query_socket(&socket, target) {
+            return set_state(Some(target), ServerState::Unreachable(e));
+        }
+        let mut answer = None;
+        receive_answers(&socket, sent_at, |from, info, ping| {
+            if from != target {
+                return true;
+            }
+            answer = Some(ServerState::Online { info, ping });
+            false
+        });
+        set_state(
+            Some(target),
+            answer.unwrap_or(ServerState::Unreachable(String::from("no answer"))),
+        );
+    });
+}
+
+//server browser screen, queries known servers and servers answering on the local network
+pub struct ServerBrowser {
+    known: Vec<String>,
+    servers: ServerList,
+    started: f64,
+    selected: usize,
+    error: Option<String>,
+}
+impl ServerBrowser {
+    pub fn new(known: Vec<String>) -> Self {
+        let mut browser = ServerBrowser {
+            known,
+            servers: Arc::new(Mutex::new(vec![])),
+            started: 0.0,
+            selected: 0,
+            error: None,
+        };
+        browser.refresh();
+        browser
+    }
+    //answers of an older scan go to the old list and are dropped with it
+    pub fn refresh(&mut self) {
+        let known: Vec<BrowsedServer> = self
+            .known
+            .iter()
+            .map(|address| BrowsedServer {
+                address: address.clone(),
+                socket_address: None,
+                state: ServerState::Querying,
+            })
+            .collect();
+        self.servers = Arc::new(Mutex::new(known));
+        self.started = get_time();
+        self.selected = 0;
+        for address in self.known.iter() {
+            query_known(Arc::clone(&self.servers), address.clone());
+        }
+        self.error = discover(Arc::clone(&self.servers)).err();
+    }
+    fn is_scanning(&self) -> bool {
+        get_time() - self.started < DISCOVERY_TIMEOUT
+    }
+    pub fn selected_server(&self) -> Option<BrowsedServer> {
+        match self.servers.lock() {
+            Ok(servers) => servers.get(self.selected).cloned(),
+            Err(e) => {
+                println!("Error while locking servers: {:?}", e);
+                None
+            }
+        }
+    }
+    //returns the next status once a server is chosen or the browser is left
+    pub fn update(&mut self) -> Option<Status> {
+        if is_key_pressed(KeyCode::Escape) {
+            return Some(Status::EnterIP);
+        }
+        if is_key_pressed(KeyCode::R) {
+            self.refresh();
+            return None;
+        }
+        let count = match self.servers.lock() {
+            Ok(servers) => servers.len(),
+            Err(e) => {
+                println!("Error while locking servers: {:?}", e);
+                0
+            }
+        };
+        if is_key_pressed(KeyCode::Down) && count > 0 {
+            self.selected = usize::min(count - 1, self.selected + 1);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            if let Some(server) = self.selected_server() {
+                if !matches!(server.state, ServerState::Unreachable(_)) {
+                    return Some(Status::EnterName);
+                }
+            }
+        }
+        None
+    }
+    pub fn draw(&self) {
+        clear_background(BLACK);
+        draw_text(
+            "Servers. Enter: join, R: refresh, Escape: back",
+            10.0,
+            20.0,
+            CONSOLE_FONT_SIZE,
+            LIGHTGRAY,
+        );
+        let header = format!(
+            "{:<20}{:<24}{:<20}{:>8}{:>8}{:>9}",
+            "Name", "Address", "Map", "Players", "Ping", "Version"
+        );
+        draw_text(header.as_str(), 10.0, 60.0, CONSOLE_FONT_SIZE, GRAY);
+        let servers = match self.servers.lock() {
+            Ok(servers) => servers,
+            Err(e) => {
+                println!("Error while locking servers: {:?}", e);
+                return;
+            }
+        };
+        let mut off_set_y = 90.0;
+        for (index, server) in servers.iter().enumerate() {
+            let (text, color) = match server.state {
+                ServerState::Querying => {
+                    (format!("{:<20}{:<24}querying...", "", server.address), GRAY)
+                }
+                ServerState::Online { ref info, ping } => (
+                    format!(
+                        "{:<20}{:<24}{:<20}{:>8}{:>8}{:>9}",
+                        info.name,
+                        server.address,
+                        info.map,
+                        info.players,
+                        format!("{}ms", ping),
+                        info.protocol
+                    ),
+                    if info.protocol == PROTOCOL_VERSION {
+                        LIGHTGRAY
+                    } else {
+                        ORANGE
+                    },
+                ),
+                ServerState::Unreachable(ref reason) => (
+                    format!("{:<20}{:<24}unreachable: {}", "", server.address, reason),
+                    RED,
+                ),
+            };
+            if index == self.selected {
+                draw_rectangle(
+                    0.0,
+                    off_set_y - 5.0 - 12.0,
+                    screen_width(),
+                    CONSOLE_FONT_SIZE + 5.0,
+                    color,
+                );
+                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
+            } else {
+                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, color);
+            }
+            off_set_y += 30.0;
+        }
+        if let Some(ref error) = self.error {
+            draw_text(error.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, RED);
+        } else if self.is_scanning() {
+            draw_text(
+                "searching the local network...",
+                10.0,
+                off_set_y,
+                CONSOLE_FONT_SIZE,
+                GRAY,
+            );
+        } else if servers.is_empty() {
+            draw_text("no servers found", 10.0, off_set_y, CONSOLE_FONT_SIZE, GRAY);
+        }
+    }
+}
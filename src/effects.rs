@@ -0,0 +1,104 @@
+use crate::models::Shield;
+use crate::preferences::*;
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+
+//burn mark on a wall, lies in the plane of the shield it was made on
+#[derive(Debug, Clone)]
+struct Decal {
+    //lower left corner, slightly in front of the wall
+    offset: Vec3,
+    u: Vec3,
+    v: Vec3,
+    //seconds since the impact
+    age: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    //seconds left
+    lifetime: f32,
+    color: Color,
+}
+
+//impact marks and sparks of shots hitting walls
+#[derive(Debug, Clone, Default)]
+pub struct Effects {
+    decals: VecDeque<Decal>,
+    particles: Vec<Particle>,
+    //impacts not yet heard
+    impacts: Vec<Vec3>,
+}
+impl Effects {
+    //direction is the one of the shot, the mark is put on the side it came from
+    pub fn wall_impact(&mut self, point: Vec3, shield: &Shield, direction: Vec3, color: Color) {
+        let mut normal = shield.u.cross(shield.v).normalize();
+        if normal.dot(direction) > 0.0 {
+            normal = -normal;
+        }
+        let u = shield.u.normalize() * DECAL_SIZE;
+        let v = shield.v.normalize() * DECAL_SIZE;
+        self.decals.push_back(Decal {
+            offset: point + normal * DECAL_WALL_OFFSET - (u + v) / 2.0,
+            u,
+            v,
+            age: 0.0,
+        });
+        self.impacts.push(point);
+        while self.decals.len() > MAX_DECALS {
+            self.decals.pop_front();
+        }
+        for _ in 0..IMPACT_PARTICLES {
+            let spread = vec3(
+                rand::gen_range(-1.0, 1.0),
+                rand::gen_range(-1.0, 1.0),
+                rand::gen_range(-1.0, 1.0),
+            );
+            self.particles.push(Particle {
+                position: point + normal * DECAL_WALL_OFFSET,
+                velocity: (normal + spread * 0.6).normalize_or_zero() * rand::gen_range(0.5, 1.5),
+                lifetime: rand::gen_range(0.2, IMPACT_PARTICLE_DURATION),
+                color,
+            });
+        }
+    }
+    //positions of the impacts since the last call, for their sound
+    pub fn take_impacts(&mut self) -> Vec<Vec3> {
+        std::mem::take(&mut self.impacts)
+    }
+    pub fn update(&mut self, delta: f32) {
+        for decal in self.decals.iter_mut() {
+            decal.age += delta;
+        }
+        self.decals.retain(|decal| decal.age < DECAL_DURATION);
+        for particle in self.particles.iter_mut() {
+            particle.velocity.y -= IMPACT_PARTICLE_GRAVITY * delta;
+            particle.position += particle.velocity * delta;
+            particle.lifetime -= delta;
+        }
+        self.particles.retain(|particle| particle.lifetime > 0.0);
+    }
+    pub fn draw(&self) {
+        for decal in &self.decals {
+            //fully visible for the first half of its time, then fading out
+            let fade = f32::min(1.0, 2.0 * (1.0 - decal.age / DECAL_DURATION));
+            draw_affine_parallelogram(
+                decal.offset,
+                decal.u,
+                decal.v,
+                None,
+                Color::new(0.1, 0.1, 0.1, 0.8 * fade),
+            );
+        }
+        for particle in &self.particles {
+            draw_cube(
+                particle.position,
+                vec3(0.01, 0.01, 0.01),
+                None,
+                particle.color,
+            );
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use crate::layout::Layout;
+use crate::models::*;
+use crate::preferences::*;
+use macroquad::miniquad::date;
+use macroquad::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedEvent {
+    //killer comes from killed_by, or from the only player who scored in the same update
+    Kill {
+        killer: Option<String>,
+        victim: String,
+    },
+    Join(String),
+    Leave(String),
+    Capture {
+        name: String,
+        team: Team,
+    },
+}
+impl FeedEvent {
+    pub fn text(&self) -> String {
+        match self {
+            FeedEvent::Kill {
+                killer: Some(killer),
+                victim,
+            } => format!("{} killed {}", killer, victim),
+            FeedEvent::Kill {
+                killer: None,
+                victim,
+            } => format!("{} died", victim),
+            FeedEvent::Join(name) => format!("{} joined", name),
+            FeedEvent::Leave(name) => format!("{} left", name),
+            FeedEvent::Capture { name, team } => {
+                format!("{} captured the {} flag", name, team.name())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FeedLine {
+    event: FeedEvent,
+    //the local player is the killer or the victim
+    involves_player: bool,
+    //seconds since the epoch, the feed is updated from the listener thread
+    time: f64,
+}
+
+#[derive(Debug, Clone)]
+struct KnownPlayer {
+    name: String,
+    score: u32,
+    killed: bool,
+    captures: u32,
+    carrying_flag: Option<Team>,
+}
+
+//events found by comparing each player list from the server with the previous one
+#[derive(Debug, Clone, Default)]
+pub struct KillFeed {
+    lines: VecDeque<FeedLine>,
+    known: HashMap<String, KnownPlayer>,
+    //the first player list only fills known players, everybody in it was already there
+    initialized: bool,
+}
+impl KillFeed {
+    pub fn update(&mut self, players: &[Player], player_id: &str) {
+        let mut events = vec![];
+        let scorers: Vec<&Player> = players
+            .iter()
+            .filter(|player| {
+                self.known
+                    .get(&player.id)
+                    .is_some_and(|known| player.score > known.score)
+            })
+            .collect();
+        for player in players {
+            let killed = matches!(player.player_status, PlayerStatus::Killed);
+            match self.known.get(&player.id) {
+                None => {
+                    if player.id != player_id
+                        && !player.spectator
+                        && !matches!(player.player_status, PlayerStatus::Disconnent)
+                    {
+                        events.push(FeedEvent::Join(player.name.clone()));
+                    }
+                }
+                Some(known) => {
+                    if let (true, Some(team)) =
+                        (player.captures > known.captures, known.carrying_flag)
+                    {
+                        events.push(FeedEvent::Capture {
+                            name: player.name.clone(),
+                            team,
+                        });
+                    }
+                    if killed && !known.killed {
+                        let killed_by = player
+                            .killed_by
+                            .as_ref()
+                            .and_then(|id| players.iter().find(|killer| killer.id == *id));
+                        let killer = match (killed_by, scorers.as_slice()) {
+                            (Some(killer), _) => Some(killer.name.clone()),
+                            (None, [killer]) if killer.id != player.id => Some(killer.name.clone()),
+                            _ => None,
+                        };
+                        events.push(FeedEvent::Kill {
+                            killer,
+                            victim: player.name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        let mut left: Vec<String> = vec![];
+        for (id, known) in self.known.iter() {
+            let gone = match players.iter().find(|player| player.id == *id) {
+                Some(player) => matches!(player.player_status, PlayerStatus::Disconnent),
+                None => true,
+            };
+            if gone && id != player_id {
+                left.push(known.name.clone());
+            }
+        }
+        left.sort();
+        events.extend(left.into_iter().map(FeedEvent::Leave));
+
+        self.known = players
+            .iter()
+            .filter(|player| !matches!(player.player_status, PlayerStatus::Disconnent))
+            .map(|player| {
+                (
+                    player.id.clone(),
+                    KnownPlayer {
+                        name: player.name.clone(),
+                        score: player.score,
+                        killed: matches!(player.player_status, PlayerStatus::Killed),
+                        captures: player.captures,
+                        carrying_flag: player.carrying_flag,
+                    },
+                )
+            })
+            .collect();
+        if !self.initialized {
+            self.initialized = true;
+            return;
+        }
+        let player_name = players
+            .iter()
+            .find(|player| player.id == player_id)
+            .map(|player| player.name.clone());
+        for event in events {
+            let involves_player = match (&event, &player_name) {
+                (FeedEvent::Kill { killer, victim }, Some(name)) => {
+                    victim == name || killer.as_ref() == Some(name)
+                }
+                (FeedEvent::Capture { name, .. }, Some(player_name)) => name == player_name,
+                _ => false,
+            };
+            self.push(event, involves_player);
+        }
+    }
+    pub fn push(&mut self, event: FeedEvent, involves_player: bool) {
+        self.lines.push_back(FeedLine {
+            event,
+            involves_player,
+            time: date::now(),
+        });
+        while self.lines.len() > FEED_VISIBLE_EVENTS {
+            self.lines.pop_front();
+        }
+    }
+    //newest event at the top, right aligned in the corner of the 3d view
+    pub fn draw(&self, font: &Font, layout: &Layout) {
+        let now = date::now();
+        let mut top_offset = layout.feed.y + layout.feed.h / FEED_VISIBLE_EVENTS as f32;
+        for line in self.lines.iter().rev() {
+            if now - line.time > FEED_EVENT_DURATION {
+                continue;
+            }
+            let text = line.event.text();
+            let font_size = GAME_FONT_SIZE;
+            let size = measure_text(text.as_str(), Some(font), font_size, layout.scale);
+            let left = layout.feed.x + layout.feed.w - size.width;
+            draw_rectangle(
+                left - 2.0 * layout.scale,
+                top_offset - size.offset_y - 2.0 * layout.scale,
+                size.width + 4.0 * layout.scale,
+                size.height + 4.0 * layout.scale,
+                Color::new(0.0, 0.0, 0.0, 0.4),
+            );
+            draw_text_ex(
+                text.as_str(),
+                left,
+                top_offset,
+                TextParams {
+                    font: Some(font),
+                    font_size,
+                    font_scale: layout.scale,
+                    font_scale_aspect: 1.0,
+                    rotation: 0.0,
+                    color: if line.involves_player { YELLOW } else { WHITE },
+                },
+            );
+            top_offset += layout.feed.h / FEED_VISIBLE_EVENTS as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: &str, name: &str) -> Player {
+        let mut player = Player::new();
+        player.id = String::from(id);
+        player.name = String::from(name);
+        player
+    }
+
+    fn events(feed: &KillFeed) -> Vec<FeedEvent> {
+        feed.lines.iter().map(|line| line.event.clone()).collect()
+    }
+
+    #[test]
+    fn first_player_list_only_fills_known_players() {
+        let mut feed = KillFeed::default();
+        feed.update(&[player("a", "alice"), player("b", "bob")], "a");
+        assert!(events(&feed).is_empty());
+    }
+
+    #[test]
+    fn join_and_leave() {
+        let mut feed = KillFeed::default();
+        feed.update(&[player("a", "alice"), player("b", "bob")], "a");
+        let mut bob = player("b", "bob");
+        bob.player_status = PlayerStatus::Disconnent;
+        feed.update(&[player("a", "alice"), bob, player("c", "carol")], "a");
+        assert_eq!(
+            events(&feed),
+            vec![
+                FeedEvent::Join(String::from("carol")),
+                FeedEvent::Leave(String::from("bob"))
+            ]
+        );
+        feed.update(&[player("a", "alice")], "a");
+        assert_eq!(
+            events(&feed).last(),
+            Some(&FeedEvent::Leave(String::from("carol")))
+        );
+    }
+
+    #[test]
+    fn kill_by_last_hitter_involves_the_local_player() {
+        let mut feed = KillFeed::default();
+        feed.update(&[player("a", "alice"), player("b", "bob")], "a");
+        let mut bob = player("b", "bob");
+        bob.player_status = PlayerStatus::Killed;
+        bob.killed_by = Some(String::from("a"));
+        feed.update(&[player("a", "alice"), bob], "a");
+        assert_eq!(
+            events(&feed),
+            vec![FeedEvent::Kill {
+                killer: Some(String::from("alice")),
+                victim: String::from("bob")
+            }]
+        );
+        assert!(feed.lines[0].involves_player);
+    }
+
+    #[test]
+    fn kill_without_killed_by_goes_to_the_only_scorer() {
+        let mut feed = KillFeed::default();
+        let players = [
+            player("a", "alice"),
+            player("b", "bob"),
+            player("c", "carol"),
+        ];
+        feed.update(&players, "a");
+        let mut bob = player("b", "bob");
+        bob.score = 1;
+        let mut carol = player("c", "carol");
+        carol.player_status = PlayerStatus::Killed;
+        feed.update(&[player("a", "alice"), bob, carol], "a");
+        assert_eq!(
+            events(&feed),
+            vec![FeedEvent::Kill {
+                killer: Some(String::from("bob")),
+                victim: String::from("carol")
+            }]
+        );
+        assert!(!feed.lines[0].involves_player);
+    }
+}
@@ -0,0 +1,461 @@
+use crate::preferences::*;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+//everything the player can do with keyboard or mouse while the game runs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    Fire,
+    Reload,
+    NextWeapon,
+    Rifle,
+    Shotgun,
+    Launcher,
+    Chat,
+    Scoreboard,
+    ToggleGrab,
+    ToggleFullscreen,
+    KeyBindings,
+    Options,
+    BrowseServers,
+    Reconnect,
+    Quit,
+}
+impl Action {
+    pub const ALL: [Action; 19] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Fire,
+        Action::Reload,
+        Action::NextWeapon,
+        Action::Rifle,
+        Action::Shotgun,
+        Action::Launcher,
+        Action::Chat,
+        Action::Scoreboard,
+        Action::ToggleGrab,
+        Action::ToggleFullscreen,
+        Action::KeyBindings,
+        Action::Options,
+        Action::BrowseServers,
+        Action::Reconnect,
+        Action::Quit,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::StrafeLeft => "Strafe left",
+            Action::StrafeRight => "Strafe right",
+            Action::Fire => "Fire",
+            Action::Reload => "Reload",
+            Action::NextWeapon => "Next weapon",
+            Action::Rifle => "Rifle",
+            Action::Shotgun => "Shotgun",
+            Action::Launcher => "Launcher",
+            Action::Chat => "Chat",
+            Action::Scoreboard => "Scoreboard (hold)",
+            Action::ToggleGrab => "Toggle mouse grab",
+            Action::ToggleFullscreen => "Toggle fullscreen",
+            Action::KeyBindings => "Key bindings",
+            Action::Options => "Options",
+            Action::BrowseServers => "Server browser",
+            Action::Reconnect => "Reconnect",
+            Action::Quit => "Quit",
+        }
+    }
+}
+
+//what the player does during one frame, read from the key bindings or produced by the bot
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Controls {
+    pub move_forward: bool,
+    pub move_backward: bool,
+    pub strafe_left: bool,
+    pub strafe_right: bool,
+    pub fire: bool,
+    pub reload: bool,
+    pub next_weapon: bool,
+    //index of the weapon picked directly
+    pub select_weapon: Option<usize>,
+    //change of yaw and pitch in radians
+    pub look: Vec2,
+}
+impl Controls {
+    pub fn from_input(
+        key_bindings: &KeyBindings,
+        mouse_delta: Vec2,
+        look_scale: f32,
+        invert_y: bool,
+    ) -> Self {
+        let pitch_direction = if invert_y { 1.0 } else { -1.0 };
+        Controls {
+            move_forward: key_bindings.is_down(Action::MoveForward),
+            move_backward: key_bindings.is_down(Action::MoveBackward),
+            strafe_left: key_bindings.is_down(Action::StrafeLeft),
+            strafe_right: key_bindings.is_down(Action::StrafeRight),
+            //held down the weapon keeps firing at its own rate
+            fire: key_bindings.is_down(Action::Fire),
+            reload: key_bindings.is_pressed(Action::Reload),
+            next_weapon: key_bindings.is_pressed(Action::NextWeapon),
+            select_weapon: [Action::Rifle, Action::Shotgun, Action::Launcher]
+                .iter()
+                .position(|action| key_bindings.is_pressed(*action)),
+            look: vec2(
+                mouse_delta.x * look_scale,
+                mouse_delta.y * look_scale * pitch_direction,
+            ),
+        }
+    }
+}
+
+//stored in the settings file by name, for example "W" or "MouseLeft"
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+impl Binding {
+    pub fn is_down(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(*key),
+            Binding::Mouse(button) => is_mouse_button_down(*button),
+        }
+    }
+    pub fn is_pressed(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(*key),
+            Binding::Mouse(button) => is_mouse_button_pressed(*button),
+        }
+    }
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse{:?}", button),
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Binding> {
+        let name = name.trim();
+        if let Some(button) = MOUSE_BUTTONS
+            .iter()
+            .find(|button| Binding::Mouse(**button).name() == name)
+        {
+            return Some(Binding::Mouse(*button));
+        }
+        KEYS.iter()
+            .find(|key| format!("{:?}", key) == name)
+            .map(|key| Binding::Key(*key))
+    }
+    //key or mouse button pressed during this frame, used when rebinding.
+    //keys that can not be stored by name are ignored, the settings file could not be loaded again
+    pub fn last_pressed() -> Option<Binding> {
+        if let Some(key) = get_last_key_pressed().filter(|key| KEYS.contains(key)) {
+            return Some(Binding::Key(key));
+        }
+        MOUSE_BUTTONS
+            .iter()
+            .find(|button| is_mouse_button_pressed(**button))
+            .map(|button| Binding::Mouse(*button))
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Binding::from_name(&name).ok_or(format!("unknown key or mouse button `{}`", name))
+    }
+}
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.name()
+    }
+}
+
+//actions missing from the settings file keep their default bindings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(
+    from = "BTreeMap<Action, Vec<Binding>>",
+    into = "BTreeMap<Action, Vec<Binding>>"
+)]
+pub struct KeyBindings {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+impl From<BTreeMap<Action, Vec<Binding>>> for KeyBindings {
+    fn from(bindings: BTreeMap<Action, Vec<Binding>>) -> Self {
+        let mut key_bindings = KeyBindings::new();
+        for (action, bindings) in bindings {
+            key_bindings.set(action, bindings);
+        }
+        key_bindings
+    }
+}
+impl From<KeyBindings> for BTreeMap<Action, Vec<Binding>> {
+    fn from(key_bindings: KeyBindings) -> Self {
+        key_bindings.bindings
+    }
+}
+impl KeyBindings {
+    pub fn new() -> Self {
+        let mut bindings = BTreeMap::new();
+        for action in Action::ALL {
+            let defaults = match action {
+                Action::MoveForward => vec![Binding::Key(KeyCode::Up), Binding::Key(KeyCode::W)],
+                Action::MoveBackward => {
+                    vec![Binding::Key(KeyCode::Down), Binding::Key(KeyCode::S)]
+                }
+                Action::StrafeLeft => vec![Binding::Key(KeyCode::Left), Binding::Key(KeyCode::A)],
+                Action::StrafeRight => {
+                    vec![Binding::Key(KeyCode::Right), Binding::Key(KeyCode::D)]
+                }
+                Action::Fire => vec![Binding::Mouse(MouseButton::Left)],
+                Action::Reload => vec![Binding::Key(KeyCode::R)],
+                Action::NextWeapon => vec![Binding::Key(KeyCode::Q)],
+                Action::Rifle => vec![Binding::Key(KeyCode::Key1)],
+                Action::Shotgun => vec![Binding::Key(KeyCode::Key2)],
+                Action::Launcher => vec![Binding::Key(KeyCode::Key3)],
+                Action::Chat => vec![Binding::Key(KeyCode::T)],
+                Action::Scoreboard => vec![Binding::Key(KeyCode::GraveAccent)],
+                Action::ToggleGrab => vec![Binding::Key(KeyCode::Tab)],
+                Action::ToggleFullscreen => vec![Binding::Key(KeyCode::F11)],
+                Action::KeyBindings => vec![Binding::Key(KeyCode::F1)],
+                Action::Options => vec![Binding::Key(KeyCode::F2)],
+                Action::BrowseServers => vec![Binding::Key(KeyCode::F3)],
+                Action::Reconnect => vec![Binding::Key(KeyCode::F5)],
+                Action::Quit => vec![Binding::Key(KeyCode::Escape)],
+            };
+            bindings.insert(action, defaults);
+        }
+        KeyBindings { bindings }
+    }
+    pub fn get(&self, action: Action) -> &[Binding] {
+        match self.bindings.get(&action) {
+            Some(bindings) => bindings,
+            None => &[],
+        }
+    }
+    pub fn set(&mut self, action: Action, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+    pub fn add(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+    pub fn is_down(&self, action: Action) -> bool {
+        self.get(action).iter().any(|binding| binding.is_down())
+    }
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.get(action).iter().any(|binding| binding.is_pressed())
+    }
+    pub fn describe(&self, action: Action) -> String {
+        self.get(action)
+            .iter()
+            .map(|binding| binding.name())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+//in-game screen for changing key bindings
+#[derive(Debug, Clone, Default)]
+pub struct RebindMenu {
+    pub selected: usize,
+    pub capturing: bool,
+}
+impl RebindMenu {
+    //returns false once the menu has been closed
+    pub fn update(&mut self, key_bindings: &mut KeyBindings) -> bool {
+        let action = Action::ALL[self.selected];
+        if self.capturing {
+            if is_key_pressed(KeyCode::Escape) {
+                self.capturing = false;
+            } else if let Some(binding) = Binding::last_pressed() {
+                key_bindings.add(action, binding);
+                self.capturing = false;
+            }
+            return true;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            return false;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = usize::min(Action::ALL.len() - 1, self.selected + 1);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            self.capturing = true;
+        }
+        if is_key_pressed(KeyCode::Backspace) || is_key_pressed(KeyCode::Delete) {
+            key_bindings.set(action, vec![]);
+        }
+        true
+    }
+    pub fn draw(&self, key_bindings: &KeyBindings) {
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.85),
+        );
+        draw_text(
+            "Key bindings. Enter: add binding, Backspace: clear, Escape: back",
+            10.0,
+            20.0,
+            CONSOLE_FONT_SIZE,
+            LIGHTGRAY,
+        );
+        let mut off_set_y = 60.0;
+        for (index, action) in Action::ALL.iter().enumerate() {
+            let bindings = if self.capturing && index == self.selected {
+                String::from("press a key or mouse button...")
+            } else {
+                key_bindings.describe(*action)
+            };
+            let text = format!("{:<20}{}", action.label(), bindings);
+            if index == self.selected {
+                draw_rectangle(
+                    0.0,
+                    off_set_y - 5.0 - 12.0,
+                    screen_width(),
+                    CONSOLE_FONT_SIZE + 5.0,
+                    LIGHTGRAY,
+                );
+                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
+            } else {
+                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, LIGHTGRAY);
+            }
+            off_set_y += 30.0;
+        }
+    }
+}
+
+const MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+//keys that can be used in bindings
+const KEYS: [KeyCode; 79] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Enter,
+    KeyCode::Escape,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+    KeyCode::Insert,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::LeftBracket,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_bindable_key_round_trips_by_name() {
+        for key in KEYS {
+            let binding = Binding::Key(key);
+            assert_eq!(Binding::from_name(&binding.name()), Some(binding));
+        }
+        for button in MOUSE_BUTTONS {
+            let binding = Binding::Mouse(button);
+            assert_eq!(Binding::from_name(&binding.name()), Some(binding));
+        }
+    }
+
+    #[test]
+    fn key_bindings_round_trip_through_json() {
+        let mut key_bindings = KeyBindings::new();
+        key_bindings.set(
+            Action::Fire,
+            vec![
+                Binding::Key(KeyCode::Space),
+                Binding::Mouse(MouseButton::Right),
+            ],
+        );
+        let json = serde_json::to_string(&key_bindings).unwrap();
+        let loaded: KeyBindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, key_bindings);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::preferences::*;
+use macroquad::prelude::*;
+
+/*
+    screen rectangles of the hud for the current window size.
+    The reference layout in preferences is designed for SCREEN_WIDTH x SCREEN_HEIGHT,
+    it is scaled uniformly to fit the window and centered
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub scale: f32,
+    pub viewport: Rect,
+    pub mini_map: Rect,
+    pub scoreboard: Rect,
+    pub chat: Rect,
+    pub feed: Rect,
+    pub score_column: f32,
+    pub line_height: f32,
+}
+impl Layout {
+    pub fn new(screen_width: f32, screen_height: f32) -> Self {
+        let scale = f32::min(
+            screen_width / SCREEN_WIDTH as f32,
+            screen_height / SCREEN_HEIGHT as f32,
+        );
+        let left = (screen_width - SCREEN_WIDTH as f32 * scale) / 2.0;
+        let top = (screen_height - SCREEN_HEIGHT as f32 * scale) / 2.0;
+        let rect = |x: f32, y: f32, w: f32, h: f32| {
+            Rect::new(left + x * scale, top + y * scale, w * scale, h * scale)
+        };
+        Layout {
+            scale,
+            viewport: rect(MAIN_MARGIN_LEFT, MAIN_MARGIN_TOP, MAIN_WIDTH, MAIN_HEIGHT),
+            mini_map: rect(MAP_MARGIN_LEFT, MAP_MARGIN_TOP, MAP_WIDTH, MAP_HEIGHT),
+            scoreboard: rect(
+                NAME_MARGIN_LEFT,
+                NAME_MARGIN_TOP,
+                SCOREBOARD_WIDTH,
+                SCREEN_HEIGHT as f32 - NAME_MARGIN_TOP,
+            ),
+            chat: rect(CHAT_MARGIN_LEFT, CHAT_MARGIN_TOP, CHAT_WIDTH, CHAT_HEIGHT),
+            feed: rect(FEED_MARGIN_LEFT, FEED_MARGIN_TOP, FEED_WIDTH, FEED_HEIGHT),
+            score_column: left + SCORE_MARGIN_LEFT * scale,
+            line_height: SCOREBOARD_LINE_HEIGHT * scale,
+        }
+    }
+    pub fn current() -> Self {
+        Layout::new(screen_width(), screen_height())
+    }
+    //size of the render target for the 3d view, never smaller than one pixel
+    pub fn viewport_size(&self) -> (u32, u32) {
+        (
+            u32::max(1, self.viewport.w as u32),
+            u32::max(1, self.viewport.h as u32),
+        )
+    }
+}
@@ -1,7 +1,7 @@
+use macroquad::miniquad::date;
 use macroquad::prelude::*;
 use serde_json::from_str;
-use std::sync::mpsc::Receiver;
-use std::{io, process::exit, usize};
+use std::{io, process::exit};
 
 mod preferences;
 use preferences::*;
@@ -12,11 +12,54 @@ use models::*;
 mod utils;
 use utils::*;
 
-use std::net::UdpSocket;
-use std::sync::{Arc, mpsc, Mutex};
+mod layout;
+use layout::*;
 
-use std::{fs, thread};
+mod input;
+use input::*;
 
+mod settings;
+use settings::*;
+
+mod cli;
+use cli::*;
+
+mod bot;
+use bot::*;
+
+mod discovery;
+use discovery::*;
+
+mod chat;
+use chat::*;
+
+mod feed;
+use feed::*;
+
+mod scoreboard;
+use scoreboard::*;
+
+mod rules;
+use rules::*;
+
+mod ctf;
+use ctf::*;
+
+mod weapons;
+use weapons::*;
+
+mod effects;
+use effects::*;
+
+mod audio;
+use audio::*;
+
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use std::thread;
 
 fn conf() -> Conf {
     Conf {
@@ -24,6 +67,7 @@ fn conf() -> Conf {
         window_width: SCREEN_WIDTH as i32,
         window_height: SCREEN_HEIGHT as i32,
         fullscreen: false,
+        window_resizable: true,
         ..Default::default()
     }
 }
@@ -31,28 +75,68 @@ fn conf() -> Conf {
 async fn main() {
     //send request from client:  echo -n 'Hello from client' | nc -u 127.0.0.1 4000
 
-    let mut move_speed = MOVE_SPEED;
-    let mut look_speed = LOOK_SPEED;
-
-    get_settings(&mut move_speed, &mut look_speed);
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("{}\n\n{}", e, USAGE);
+            exit(1);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        exit(0);
+    }
 
-    let mut status = Status::EnterIP;
-    let mut server_addr = String::new();
-    let mut player_name = String::new();
-    let mut map_path = String::from(DEFAULT_MAP_PATH);
+    let settings_path = args.settings.as_deref().unwrap_or(SETTINGS_PATH);
+    let mut settings = match Settings::load(settings_path) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("Error while loading settings: {}", e);
+            Settings {
+                path: String::from(settings_path),
+                load_error: Some(format!("{}", e)),
+                ..Default::default()
+            }
+        }
+    };
+    let mut options_menu: Option<OptionsMenu> = None;
+    let mut server_browser: Option<ServerBrowser> = None;
+
+    let mut status = args.start_status();
+    let mut server_addr = args
+        .server
+        .clone()
+        .unwrap_or(settings.server_address.clone());
+    let mut player_name = args.name.clone().unwrap_or(settings.player_name.clone());
+    let mut team: Option<Team> = None;
+    let mut selected_team_index = 0;
+    let mut map_path = if let Some(ref map) = args.map {
+        map.clone()
+    } else if settings.default_map.is_empty() {
+        String::from(DEFAULT_MAP_PATH)
+    } else {
+        settings.default_map.clone()
+    };
 
     let mut game_params: Option<GameParams> = None;
     let mut player: Option<Arc<Mutex<Player>>> = None;
 
     let font = load_ttf_font("fonts/AltoMono.ttf").await.unwrap();
+    //sounds are loaded once they are wanted, a muted game never loads them
+    let mut audio = Audio::default();
 
     let mut grabbed = true;
     set_cursor_grab(grabbed);
     show_mouse(!grabbed);
 
     let enemies: Arc<Mutex<Option<Vec<Player>>>> = Arc::new(Mutex::new(None));
+    let match_status = Arc::new(Mutex::new(MatchStatus::default()));
 
-    let socket = Arc::new(UdpSocket::bind("0.0.0.0:0").unwrap());
+    //bound once the server address is resolved, to match its address family
+    let mut socket: Option<Arc<UdpSocket>> = None;
+    let mut server: Option<SocketAddr> = None;
+    let mut address_error: Option<String> = None;
+    let mut pending_connection: Option<PendingConnection> = None;
 
     //let (tx, rx) = mpsc::channel();
 
@@ -63,9 +147,18 @@ async fn main() {
     let mut frame_counter: u32 = 0;
     let mut prev_time = get_ms();
 
-    let mut selexted_map_index = 0;
+    let mut selexted_map_index = default_map_index(&settings);
+
+    let mut fullscreen = false;
 
     loop {
+        if !settings.mute && !audio.is_loaded() {
+            audio = Audio::load().await;
+        }
+        if settings.key_bindings.is_pressed(Action::ToggleFullscreen) {
+            fullscreen = !fullscreen;
+            set_fullscreen(fullscreen);
+        }
         frame_counter += 1;
         if frame_counter > 60 {
             let current_time = get_ms();
@@ -80,9 +173,42 @@ async fn main() {
                 }
             }
         }
+        let is_entry_screen = matches!(
+            status,
+            Status::EnterIP | Status::EnterName | Status::SelectTeam | Status::SelectMap
+        );
+        if is_entry_screen && settings.key_bindings.is_pressed(Action::Options) {
+            options_menu = Some(OptionsMenu::new(&settings, status));
+            status = Status::Options;
+        }
+        if status == Status::EnterIP && settings.key_bindings.is_pressed(Action::BrowseServers) {
+            let mut known = settings.recent_servers.clone();
+            if !server_addr.is_empty() && !known.contains(&server_addr) {
+                known.insert(0, server_addr.clone());
+            }
+            server_browser = Some(ServerBrowser::new(known));
+            status = Status::BrowseServers;
+        }
+        //join the last server again without going through the entry screens
+        if is_entry_screen
+            && settings.key_bindings.is_pressed(Action::Reconnect)
+            && !server_addr.is_empty()
+            && player_name.len() > 2
+        {
+            status = Status::Init;
+        }
         match status {
-            Status::EnterIP => handle_ip_input(&mut status, &mut server_addr),
+            Status::EnterIP => handle_ip_input(
+                &mut status,
+                &mut server_addr,
+                &settings.recent_servers,
+                &mut address_error,
+                &mut pending_connection,
+            ),
             Status::EnterName => handle_name_input(&mut status, &mut player_name, &server_addr),
+            Status::SelectTeam => {
+                select_team_handler(&mut status, &mut team, &mut selected_team_index)
+            }
             Status::SelectMap => select_map_handler(
                 &mut status,
                 &mut map_path,
@@ -90,22 +216,67 @@ async fn main() {
                 &player_name,
                 &mut selexted_map_index,
             ),
-            Status::Init => init_game_handler(
-                &mut status,
-                &mut game_params,
-                &mut player,
-                &player_name,
-                &map_path,
-            ),
+            Status::Init => {
+                //a lookup started for another address before a screen change is not used
+                if pending_connection
+                    .as_ref()
+                    .is_none_or(|lookup| lookup.address != server_addr)
+                {
+                    pending_connection = Some(PendingConnection::start(&server_addr));
+                }
+                match pending_connection.as_ref().and_then(|lookup| lookup.take()) {
+                    None => {
+                        clear_background(BLACK);
+                        draw_text(
+                            format!("Connecting to {}...", server_addr).as_str(),
+                            10.0,
+                            20.0,
+                            CONSOLE_FONT_SIZE,
+                            LIGHTGRAY,
+                        );
+                    }
+                    Some(Ok((_server, _socket))) => {
+                        pending_connection = None;
+                        server = Some(_server);
+                        socket = Some(Arc::new(_socket));
+                        //a settings file that failed to load is not overwritten, bots keep the profile as it is
+                        if settings.load_error.is_none() && !args.bot {
+                            settings.remember_session(&server_addr, &player_name, &map_path);
+                            if let Err(e) = settings.save() {
+                                println!("Error while saving settings: {}", e);
+                            }
+                        }
+                        init_game_handler(
+                            &mut status,
+                            &mut game_params,
+                            &mut player,
+                            &player_name,
+                            &map_path,
+                            team,
+                            &args,
+                        )
+                    }
+                    Some(Err(e)) => {
+                        pending_connection = None;
+                        println!("Error while connecting to {}: {}", server_addr, e);
+                        address_error = Some(e);
+                        status = Status::EnterIP;
+                    }
+                }
+            }
             Status::StartServerListener => {
-                if let Some(ref mut _game_params) = game_params {
+                if let (Some(ref mut _game_params), Some(ref _socket)) = (&mut game_params, &socket)
+                {
                     if let Some(ref _player) = player {
                         start_server_listener(
-                            Arc::clone(&socket),
+                            Arc::clone(_socket),
                             Arc::clone(&enemies),
                             _player.clone(),
                             Arc::clone(&_game_params.hittables),
-                            server_addr.clone(),
+                            Arc::clone(&match_status),
+                            Arc::clone(&_game_params.chat.log),
+                            Arc::clone(&_game_params.feed),
+                            Arc::clone(&_game_params.roster),
                         );
                         status = Status::Run;
                     } else {
@@ -118,20 +289,23 @@ async fn main() {
                 }
             }
             Status::Run => {
-                if let Some(ref mut _game_params) = game_params {
+                if let (Some(ref mut _game_params), Some(ref _socket), Some(_server)) =
+                    (&mut game_params, &socket, server)
+                {
                     if let Some(ref _player) = player {
                         handle_game_run(
-                            &server_addr,
-                            Arc::clone(&_player),
+                            _server,
+                            Arc::clone(_player),
                             _game_params,
-                            &socket,
+                            _socket,
                             Arc::clone(&enemies),
+                            Arc::clone(&match_status),
                             &mut is_first_tun,
                             fps,
                             &font,
-                            move_speed,
-                            look_speed,
+                            &mut settings,
                             &mut grabbed,
+                            &mut audio,
                         );
                     } else {
                         println!("error while initialisation player");
@@ -142,10 +316,91 @@ async fn main() {
                     exit(0);
                 }
             }
+            Status::Options => match options_menu {
+                Some(ref mut menu) => {
+                    if menu.update(&mut settings) {
+                        menu.draw();
+                    } else {
+                        status = menu.return_to;
+                        options_menu = None;
+                        //saved values fill in whatever has not been entered yet
+                        if server_addr.is_empty() {
+                            server_addr = settings.server_address.clone();
+                        }
+                        if player_name.is_empty() {
+                            player_name = settings.player_name.clone();
+                        }
+                        if !settings.default_map.is_empty() {
+                            map_path = settings.default_map.clone();
+                            selexted_map_index = default_map_index(&settings);
+                        }
+                    }
+                }
+                None => status = Status::EnterIP,
+            },
+            Status::BrowseServers => match server_browser {
+                Some(ref mut browser) => match browser.update() {
+                    Some(Status::EnterName) => {
+                        if let Some(chosen) = browser.selected_server() {
+                            server_addr = chosen.address;
+                            address_error = None;
+                            //preselect the map of the server when it exists locally
+                            if let ServerState::Online { ref info, .. } = chosen.state {
+                                let maps = list_maps(MAPS_DIRECTORY_PATH);
+                                let server_map = Path::new(&info.map).file_name();
+                                if let Some(index) = maps.iter().position(|map| {
+                                    server_map.is_some() && Path::new(map).file_name() == server_map
+                                }) {
+                                    map_path = maps[index].clone();
+                                    selexted_map_index = index as i32;
+                                }
+                            }
+                        }
+                        status = Status::EnterName;
+                        server_browser = None;
+                    }
+                    Some(next) => {
+                        status = next;
+                        server_browser = None;
+                    }
+                    None => browser.draw(),
+                },
+                None => status = Status::EnterIP,
+            },
+        }
+        if is_entry_screen {
+            draw_text(
+                format!(
+                    "{}: options    {}: server browser    {}: reconnect",
+                    settings.key_bindings.describe(Action::Options),
+                    settings.key_bindings.describe(Action::BrowseServers),
+                    settings.key_bindings.describe(Action::Reconnect)
+                )
+                .as_str(),
+                10.0,
+                screen_height() - 10.0,
+                CONSOLE_FONT_SIZE,
+                GRAY,
+            );
+            if let Some(ref error) = settings.load_error {
+                draw_text(
+                    error.as_str(),
+                    10.0,
+                    screen_height() - 35.0,
+                    CONSOLE_FONT_SIZE,
+                    RED,
+                );
+            }
         }
         next_frame().await;
     }
 }
+fn default_map_index(settings: &Settings) -> i32 {
+    list_maps(MAPS_DIRECTORY_PATH)
+        .iter()
+        .position(|map| *map == settings.default_map)
+        .unwrap_or(0) as i32
+}
 fn init_player(game_params: &GameParams, player_name: &String, map_path: &String) -> Player {
     let mut player = Player::new();
     player.name = String::from(player_name);
@@ -174,17 +429,15 @@ fn init_player(game_params: &GameParams, player_name: &String, map_path: &String
     };
     player
 }
-fn init_game_params(map_path: &String) -> GameParams {
+fn init_game_params(map_path: &str) -> GameParams {
     let wall_texture =
         Texture2D::from_file_with_format(include_bytes!("../assets/bricks.png"), None);
     let arrow_texture =
         Texture2D::from_file_with_format(include_bytes!("../assets/small_arrow.png"), None);
-    let eye_texture =
-        Image::from_file_with_format(include_bytes!("../assets/eye_texture.png"), None).unwrap();
     let floor_texture =
         Texture2D::from_file_with_format(include_bytes!("../assets/patio448.png"), None);
 
-    let mini_map = match parse_map(map_path) {
+    let (mini_map, flag_bases) = match parse_map(map_path) {
         Ok(map) => map,
         Err(error) => {
             println!("Problem opening the file: {error:?}");
@@ -197,26 +450,29 @@ fn init_game_params(map_path: &String) -> GameParams {
     let mini_map_height_as_f32 = mini_map_height_as_usize as f32;
     let mini_map_length_as_f32 = mini_map_length_as_usize as f32;
 
+    let layout = Layout::current();
     let mini_map_config = MiniMapConfig::new(
         &mini_map,
-        MAP_WIDTH,
-        MAP_HEIGHT,
-        MAP_MARGIN_LEFT,
-        MAP_MARGIN_TOP,
+        layout.mini_map.w,
+        layout.mini_map.h,
+        layout.mini_map.x,
+        layout.mini_map.y,
         BLACK,
     );
-    let render_target = render_target_ex(
-        MAIN_WIDTH as u32,
-        MAIN_HEIGHT as u32,
-        RenderTargetParams {
-            sample_count: 1,
-            depth: true,
-        },
-    );
+    let render_target = create_render_target(&layout);
     let world_up = vec3(0.0, 1.0, 0.0);
     let last_mouse_position: Vec2 = mouse_position().into();
 
     let shots = vec![];
+    let rebind_menu = None;
+    let bot = None;
+    let enemy_shots = HashMap::new();
+    let enemy_hits = HashMap::new();
+    let chat = Chat::default();
+    let feed = Arc::new(Mutex::new(KillFeed::default()));
+    let roster = Arc::new(Mutex::new(vec![]));
+    let last_ping_query = 0.0;
+    let round = 0;
 
     let hittables = Arc::new(Mutex::new(vec![]));
     add_shields(Arc::clone(&hittables), &mini_map);
@@ -224,9 +480,9 @@ fn init_game_params(map_path: &String) -> GameParams {
     GameParams {
         wall_texture,
         arrow_texture,
-        eye_texture,
         floor_texture,
         mini_map_config,
+        layout,
         render_target,
         last_mouse_position,
         mini_map,
@@ -236,10 +492,56 @@ fn init_game_params(map_path: &String) -> GameParams {
         mini_map_length_as_f32,
         world_up,
         shots,
+        projectiles: vec![],
+        seen_launches: HashSet::new(),
+        weapons: Weapons::default(),
+        effects: Effects::default(),
+        rebind_menu,
+        bot,
+        enemy_shots,
+        enemy_hits,
+        chat,
+        feed,
+        roster,
+        last_ping_query,
+        round,
+        flag_bases,
         hittables,
     }
 }
-fn parse_map(file_path: &str) -> Result<Vec<Vec<bool>>, io::Error> {
+fn create_render_target(layout: &Layout) -> RenderTarget {
+    let (width, height) = layout.viewport_size();
+    render_target_ex(
+        width,
+        height,
+        RenderTargetParams {
+            sample_count: 1,
+            depth: true,
+        },
+    )
+}
+//recreate everything that depends on the window size once it changes
+fn update_layout(game_params: &mut GameParams) {
+    let layout = Layout::current();
+    if layout == game_params.layout {
+        return;
+    }
+    if layout.viewport_size() != game_params.layout.viewport_size() {
+        game_params.render_target = create_render_target(&layout);
+    }
+    game_params.mini_map_config = MiniMapConfig::new(
+        &game_params.mini_map,
+        layout.mini_map.w,
+        layout.mini_map.h,
+        layout.mini_map.x,
+        layout.mini_map.y,
+        game_params.mini_map_config.cell_color,
+    );
+    game_params.layout = layout;
+}
+//walls of the map and the cell centers of its flag bases
+type ParsedMap = (Vec<Vec<bool>>, Vec<(Team, Vec2)>);
+fn parse_map(file_path: &str) -> Result<ParsedMap, io::Error> {
     let content = read_file(file_path)?;
     if !is_map_valid(&content) {
         return Err(io::Error::new(
@@ -247,11 +549,17 @@ fn parse_map(file_path: &str) -> Result<Vec<Vec<bool>>, io::Error> {
             "Invalid Map Format",
         ));
     }
-    Ok(map_to_slice(&content))
+    let mut flag_bases = vec![];
+    for (team, marker) in [(Team::Red, FLAG_BASE_RED), (Team::Blue, FLAG_BASE_BLUE)] {
+        for (x, z) in find_markers(&content, marker) {
+            flag_bases.push((team, vec2(x as f32, z as f32)));
+        }
+    }
+    Ok((map_to_slice(&content), flag_bases))
 }
-fn render_mini_map(mini_map: &Vec<Vec<bool>>, mini_map_config: &MiniMapConfig) {
-    let mut horizontal_offset: f32 = mini_map_config.horizontal_offset as f32;
-    let mut vertical_offset: f32 = mini_map_config.vertical_offset as f32;
+fn render_mini_map(mini_map: &[Vec<bool>], mini_map_config: &MiniMapConfig) {
+    let mut horizontal_offset: f32 = mini_map_config.horizontal_offset;
+    let mut vertical_offset: f32 = mini_map_config.vertical_offset;
     for line in mini_map {
         for cell in line {
             if *cell {
@@ -265,11 +573,11 @@ fn render_mini_map(mini_map: &Vec<Vec<bool>>, mini_map_config: &MiniMapConfig) {
             }
             horizontal_offset += mini_map_config.cell_width
         }
-        horizontal_offset = mini_map_config.horizontal_offset as f32;
+        horizontal_offset = mini_map_config.horizontal_offset;
         vertical_offset += mini_map_config.cell_height;
     }
 }
-fn generate_position(map: &Vec<Vec<bool>>) -> Vec3 {
+fn generate_position(map: &[Vec<bool>]) -> Vec3 {
     let mut spaces: Vec<(usize, usize)> = vec![];
     for (z, line) in map.iter().enumerate() {
         for (x, cell) in line.iter().enumerate() {
@@ -286,235 +594,18 @@ fn generate_position(map: &Vec<Vec<bool>>) -> Vec3 {
 }
 fn draw_player_on_mini_map(
     player: &Player,
-    mini_map: &Vec<Vec<bool>>,
+    mini_map: &[Vec<bool>],
     config: &MiniMapConfig,
     up_texture: &Texture2D,
 ) {
     let image_size = f32::min(config.cell_width, config.cell_height);
-
-    //current position
-    let index_x = f32::floor(player.position.x + 0.5);
-    let index_z = f32::floor(player.position.z + 0.5);
-    let index_x_usize = index_x as usize;
-    let index_z_usize = index_z as usize;
-
-    let mut x = config.horizontal_offset + player.position.x * config.cell_width;
-    let mut z = config.vertical_offset + player.position.z * config.cell_height;
-
-    //horizontal tunnel + horizontal pockets
-    if mini_map[index_z_usize + 1][index_x_usize] && mini_map[index_z_usize - 1][index_x_usize] {
-        z = config.vertical_offset + index_z * config.cell_height;
-        //pocket on the right
-        if mini_map[index_z_usize][index_x_usize + 1] {
-            let max_x = config.horizontal_offset + index_x * config.cell_width;
-            if x > max_x {
-                x = max_x
-            }
-        }
-        //pocket on the left
-        if mini_map[index_z_usize][index_x_usize - 1] {
-            let min_x = config.horizontal_offset + index_x * config.cell_width;
-            if x < min_x {
-                x = min_x
-            }
-        }
-    }
-
-    //vertical tunnel + vertical pocket
-    if mini_map[index_z_usize][index_x_usize + 1] && mini_map[index_z_usize][index_x_usize - 1] {
-        x = config.horizontal_offset + index_x * config.cell_width;
-        //pocket up
-        if mini_map[index_z_usize - 1][index_x_usize] {
-            let min_z = config.vertical_offset + index_z * config.cell_height;
-            if z < min_z {
-                z = min_z;
-            }
-        }
-        //pocket down
-        if mini_map[index_z_usize + 1][index_x_usize] {
-            let max_z = config.vertical_offset + index_z * config.cell_height;
-            if z > max_z {
-                z = max_z;
-            }
-        }
-    }
-
-    /*
-    top left corner
-
-      ____
-     | ↗
-     |
-
-    */
-    if mini_map[index_z_usize][index_x_usize - 1]
-        && mini_map[index_z_usize - 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize + 1]
-        && !mini_map[index_z_usize + 1][index_x_usize]
-    {
-        let min_z = config.vertical_offset + index_z * config.cell_height;
-        let min_x = config.horizontal_offset + index_x * config.cell_width;
-        if x < min_x {
-            x = min_x;
-        }
-        if z < min_z {
-            z = min_z;
-        }
-    }
-
-    /*
-    top right corner
-    ____
-       ↗|
-        |
-
-    */
-
-    if mini_map[index_z_usize - 1][index_x_usize]
-        && mini_map[index_z_usize][index_x_usize + 1]
-        && !mini_map[index_z_usize][index_x_usize - 1]
-        && !mini_map[index_z_usize + 1][index_x_usize]
-    {
-        let max_x = config.horizontal_offset + index_x * config.cell_width;
-        let min_z = config.vertical_offset + index_z * config.cell_height;
-        if x > max_x {
-            x = max_x;
-        }
-        if z < min_z {
-            z = min_z;
-        }
-    }
-
-    /*
-        bottom right corner
-
-            |
-           ↗|
-        ----
-
-    */
-    if mini_map[index_z_usize][index_x_usize + 1]
-        && mini_map[index_z_usize + 1][index_x_usize]
-        && !mini_map[index_z_usize - 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize - 1]
-    {
-        let max_x = config.horizontal_offset + index_x * config.cell_width;
-        let max_z = config.vertical_offset + index_z * config.cell_height;
-        if x > max_x {
-            x = max_x
-        }
-        if z > max_z {
-            z = max_z
-        }
-    }
-
-    /*
-       bottom left corner
-
-       |
-       |↗
-       -----
-
-    */
-
-    if mini_map[index_z_usize][index_x_usize - 1]
-        && mini_map[index_z_usize + 1][index_x_usize]
-        && !mini_map[index_z_usize - 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize + 1]
-    {
-        let min_x = config.horizontal_offset + index_x * config.cell_width;
-        let max_z = config.vertical_offset + index_z * config.cell_height;
-        if x < min_x {
-            x = min_x
-        }
-        if z > max_z {
-            z = max_z
-        }
-    }
-
-    /*
-    t-junction
-
-    __________
-    ___  ↗ ___
-       |  |
-       |  |
-
-    */
-    if mini_map[index_z_usize - 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize - 1]
-        && !mini_map[index_z_usize][index_x_usize + 1]
-        && !mini_map[index_z_usize + 1][index_x_usize]
-    {
-        let min_z = config.vertical_offset + index_z * config.cell_height;
-        if z < min_z {
-            z = min_z;
-        }
-    }
-
-    /*
-     right t-junction
-
-    |  |__
-    | ↗ __
-    |  |
-
-    */
-    if mini_map[index_z_usize][index_x_usize - 1]
-        && !mini_map[index_z_usize + 1][index_x_usize]
-        && !mini_map[index_z_usize - 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize + 1]
-    {
-        let min_x = config.horizontal_offset + index_x * config.cell_width;
-        if x < min_x {
-            x = min_x;
-        }
-    }
-
-    /*
-        left t-junction
-
-         ____|  |
-         ____  ↗|
-             |  |
-
-    */
-    if mini_map[index_z_usize][index_x_usize + 1]
-        && !mini_map[index_z_usize - 1][index_x_usize]
-        && !mini_map[index_z_usize + 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize - 1]
-    {
-        let max_x = config.horizontal_offset + index_x * config.cell_width;
-        if x > max_x {
-            x = max_x;
-        }
-    }
-
-    /*
-       up t-junction
-
-         ___|  |___
-              ↗
-         -----------
-
-    */
-
-    if mini_map[index_z_usize + 1][index_x_usize]
-        && !mini_map[index_z_usize][index_x_usize - 1]
-        && !mini_map[index_z_usize][index_x_usize + 1]
-        && !mini_map[index_z_usize - 1][index_x_usize]
-    {
-        let max_z = config.vertical_offset + index_z * config.cell_height;
-        if z > max_z {
-            z = max_z;
-        }
-    }
+    let marker = mini_map_marker_position(&player.position, mini_map, config);
 
     let size = vec2(image_size, image_size);
     draw_texture_ex(
         up_texture,
-        x,
-        z,
+        marker.x,
+        marker.y,
         WHITE,
         DrawTextureParams {
             dest_size: Some(size),
@@ -526,252 +617,66 @@ fn draw_player_on_mini_map(
         },
     );
 }
-/*
 fn draw_enemy_on_minimap(
-    player: &Player,
-    mini_map: &Vec<Vec<bool>>,
+    enemy: &Player,
+    mini_map: &[Vec<bool>],
     config: &MiniMapConfig,
     color: Color,
 ) {
     let image_size = f32::min(config.cell_width, config.cell_height);
-
-    //current position
-    let index_x = f32::floor(player.position.x + 0.5);
-    let index_z = f32::floor(player.position.z + 0.5);
-
-    let mut x = config.horizontal_offset as f32 + player.position.x * config.cell_width;
-    let mut z = config.vertical_offset as f32 + player.position.z * config.cell_height;
-
-    //horizontal tunnel + horizontal pockets
-    if mini_map[index_z as usize + 1][index_x as usize]
-        && mini_map[index_z as usize - 1][index_x as usize]
-    {
-        z = config.vertical_offset as f32 + index_z * config.cell_height;
-        //pocket on the right
-        if mini_map[index_z as usize][index_x as usize + 1] {
-            let max_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-            if x > max_x {
-                x = max_x
-            }
-        }
-        //pocket on the left
-        if mini_map[index_z as usize][index_x as usize - 1] {
-            let min_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-            if x < min_x {
-                x = min_x
-            }
-        }
-    }
-
-    //vertical tunnel + vertical pocket
-    if mini_map[index_z as usize][index_x as usize + 1]
-        && mini_map[index_z as usize][index_x as usize - 1]
-    {
-        x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        //pocket up
-        if mini_map[index_z as usize - 1][index_x as usize] {
-            let min_z = config.vertical_offset as f32 + index_z * config.cell_height;
-            if z < min_z {
-                z = min_z;
-            }
-        }
-        //pocket down
-        if mini_map[index_z as usize + 1][index_x as usize] {
-            let max_z = config.vertical_offset as f32 + index_z * config.cell_height;
-            if z > max_z {
-                z = max_z;
-            }
-        }
-    }
-
-    /*
-    top left corner
-
-      ____
-     | ↗
-     |
-
-    */
-    if mini_map[index_z as usize][index_x as usize - 1]
-        && mini_map[index_z as usize - 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize + 1]
-        && !mini_map[index_z as usize + 1][index_x as usize]
-    {
-        let min_z = config.vertical_offset as f32 + index_z * config.cell_height;
-        let min_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        if x < min_x {
-            x = min_x;
-        }
-        if z < min_z {
-            z = min_z;
-        }
-    }
-
-    /*
-    top right corner
-    ____
-       ↗|
-        |
-
-    */
-
-    if mini_map[index_z as usize - 1][index_x as usize]
-        && mini_map[index_z as usize][index_x as usize + 1]
-        && !mini_map[index_z as usize][index_x as usize - 1]
-        && !mini_map[index_z as usize + 1][index_x as usize]
-    {
-        let max_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        let min_z = config.vertical_offset as f32 + index_z * config.cell_height;
-        if x > max_x {
-            x = max_x;
-        }
-        if z < min_z {
-            z = min_z;
-        }
-    }
-
-    /*
-        bottom right corner
-
-            |
-           ↗|
-        ----
-
-    */
-    if mini_map[index_z as usize][index_x as usize + 1]
-        && mini_map[index_z as usize + 1][index_x as usize]
-        && !mini_map[index_z as usize - 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize - 1]
-    {
-        let max_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        let max_z = config.vertical_offset as f32 + index_z * config.cell_height;
-        if x > max_x {
-            x = max_x
-        }
-        if z > max_z {
-            z = max_z
-        }
-    }
-
-    /*
-       bottom left corner
-
-       |
-       |↗
-       -----
-
-    */
-
-    if mini_map[index_z as usize][index_x as usize - 1]
-        && mini_map[index_z as usize + 1][index_x as usize]
-        && !mini_map[index_z as usize - 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize + 1]
-    {
-        let min_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        let max_z = config.vertical_offset as f32 + index_z * config.cell_height;
-        if x < min_x {
-            x = min_x
-        }
-        if z > max_z {
-            z = max_z
-        }
-    }
-
-    /*
-    t-junction
-
-    __________
-    ___  ↗ ___
-       |  |
-       |  |
-
-    */
-    if mini_map[index_z as usize - 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize - 1]
-        && !mini_map[index_z as usize][index_x as usize + 1]
-        && !mini_map[index_z as usize + 1][index_x as usize]
-    {
-        let min_z = config.vertical_offset as f32 + index_z * config.cell_height;
-        if z < min_z {
-            z = min_z;
-        }
-    }
-
-    /*
-     right t-junction
-
-    |  |__
-    | ↗ __
-    |  |
-
-    */
-    if mini_map[index_z as usize][index_x as usize - 1]
-        && !mini_map[index_z as usize + 1][index_x as usize]
-        && !mini_map[index_z as usize - 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize + 1]
-    {
-        let min_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        if x < min_x {
-            x = min_x;
-        }
-    }
-
-    /*
-        left t-junction
-
-         ____|  |
-         ____  ↗|
-             |  |
-
-    */
-    if mini_map[index_z as usize][index_x as usize + 1]
-        && !mini_map[index_z as usize - 1][index_x as usize]
-        && !mini_map[index_z as usize + 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize - 1]
-    {
-        let max_x = config.horizontal_offset as f32 + index_x * config.cell_width;
-        if x > max_x {
-            x = max_x;
-        }
-    }
-
-    /*
-       up t-junction
-
-         ___|  |___
-              ↗
-         -----------
-
-    */
-
-    if mini_map[index_z as usize + 1][index_x as usize]
-        && !mini_map[index_z as usize][index_x as usize - 1]
-        && !mini_map[index_z as usize][index_x as usize + 1]
-        && !mini_map[index_z as usize - 1][index_x as usize]
-    {
-        let max_z = config.vertical_offset as f32 + index_z * config.cell_height;
-        if z > max_z {
-            z = max_z;
-        }
-    }
-
-    /*
-        right pocket
-        ___
-          ↗|
-        ---
-    */
+    let marker = mini_map_marker_position(&enemy.position, mini_map, config);
     draw_circle(
-        x + image_size / 2.0,
-        z + image_size / 2.0,
+        marker.x + image_size / 2.0,
+        marker.y + image_size / 2.0,
         image_size / 2.5,
         color,
     );
 }
-*/
-
-fn draw_walls(mini_map: &Vec<Vec<bool>>, texture: Option<&Texture2D>, color: Color) {
-    for (z, line) in mini_map.into_iter().enumerate() {
+//base outline and a smaller square where the flag is
+fn draw_flags_on_minimap(flags: &[Flag], mini_map: &[Vec<bool>], config: &MiniMapConfig) {
+    let image_size = f32::min(config.cell_width, config.cell_height);
+    for flag in flags {
+        let base =
+            mini_map_marker_position(&Position::build(flag.base.x, flag.base.y), mini_map, config);
+        draw_rectangle_lines(
+            base.x,
+            base.y,
+            image_size,
+            image_size,
+            2.0,
+            flag.team.color(),
+        );
+        let marker = mini_map_marker_position(
+            &Position::build(flag.position.x, flag.position.y),
+            mini_map,
+            config,
+        );
+        draw_rectangle(
+            marker.x + image_size / 4.0,
+            marker.y + image_size / 4.0,
+            image_size / 2.0,
+            image_size / 2.0,
+            flag.team.color(),
+        );
+    }
+}
+//top left corner of a cell sized marker, clamped so it does not overlap walls
+fn mini_map_marker_position(
+    position: &Position,
+    mini_map: &[Vec<bool>],
+    config: &MiniMapConfig,
+) -> Vec2 {
+    let index_x = f32::floor(position.x + 0.5) as i32;
+    let index_z = f32::floor(position.z + 0.5) as i32;
+    let walls = neighbour_walls(mini_map, index_x, index_z);
+    let clamped = clamp_to_cell(vec2(position.x, position.z), index_x, index_z, walls);
+    vec2(
+        config.horizontal_offset + clamped.x * config.cell_width,
+        config.vertical_offset + clamped.y * config.cell_height,
+    )
+}
+fn draw_walls(mini_map: &[Vec<bool>], texture: Option<&Texture2D>, color: Color) {
+    for (z, line) in mini_map.iter().enumerate() {
         for (x, cell) in line.iter().enumerate() {
             if *cell {
                 let position = vec3(x as f32, 1.0, z as f32);
@@ -781,13 +686,8 @@ fn draw_walls(mini_map: &Vec<Vec<bool>>, texture: Option<&Texture2D>, color: Col
         }
     }
 }
-fn handle_wall_collisions(
-    mini_map: &Vec<Vec<bool>>,
-    prev_pos: Vec3,
-    position: &mut Vec3,
-    gap: f32,
-) {
-    let mut pos = position.clone();
+fn handle_wall_collisions(mini_map: &[Vec<bool>], prev_pos: Vec3, position: &mut Vec3, gap: f32) {
+    let mut pos = *position;
     pos.z = prev_pos.z;
     let points = [
         (pos.x + 0.5 + gap, pos.z + 0.5 + gap),
@@ -803,7 +703,7 @@ fn handle_wall_collisions(
         }
     }
 
-    let mut pos = position.clone();
+    let mut pos = *position;
     pos.x = prev_pos.x;
 
     let points = [
@@ -821,10 +721,16 @@ fn handle_wall_collisions(
     }
 }
 //handlers
-fn handle_ip_input(status: &mut Status, server_addr: &mut String) {
+fn handle_ip_input(
+    status: &mut Status,
+    server_addr: &mut String,
+    recent_servers: &[String],
+    address_error: &mut Option<String>,
+    pending_connection: &mut Option<PendingConnection>,
+) {
     clear_background(BLACK);
     let mut server_addr_display =
-        "Enter server IP address. Example: 127.0.0.1:4000    ".to_string();
+        "Enter server address. Example: 127.0.0.1:4000, localhost or [::1]:4000    ".to_string();
     server_addr_display.push_str(server_addr);
     draw_text(
         server_addr_display.as_str(),
@@ -833,14 +739,78 @@ fn handle_ip_input(status: &mut Status, server_addr: &mut String) {
         CONSOLE_FONT_SIZE,
         LIGHTGRAY,
     );
+    if let Some(ref error) = address_error {
+        draw_text(error.as_str(), 10.0, 45.0, CONSOLE_FONT_SIZE, RED);
+    }
+    //input waits until the entered address is resolved, a lookup of an address changed meanwhile is dropped
+    if pending_connection
+        .as_ref()
+        .is_some_and(|lookup| lookup.address != *server_addr)
+    {
+        *pending_connection = None;
+    }
+    if let Some(ref lookup) = pending_connection {
+        match lookup.take() {
+            None => {
+                draw_text("resolving...", 10.0, 45.0, CONSOLE_FONT_SIZE, GRAY);
+                return;
+            }
+            Some(Ok(_)) => {
+                *address_error = None;
+                *status = Status::EnterName;
+            }
+            Some(Err(e)) => *address_error = Some(e),
+        }
+        *pending_connection = None;
+        return;
+    }
+
+    //recent servers, Up and Down put them into the address field
+    if !recent_servers.is_empty() {
+        draw_text("Recent servers:", 10.0, 70.0, CONSOLE_FONT_SIZE, LIGHTGRAY);
+        let current = recent_servers
+            .iter()
+            .position(|recent| recent == server_addr);
+        let mut off_set_y = 100.0;
+        for (index, recent) in recent_servers.iter().enumerate() {
+            if Some(index) == current {
+                draw_rectangle(
+                    0.0,
+                    off_set_y - 5.0 - 12.0,
+                    screen_width(),
+                    CONSOLE_FONT_SIZE + 5.0,
+                    LIGHTGRAY,
+                );
+                draw_text(recent, 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
+            } else {
+                draw_text(recent, 10.0, off_set_y, CONSOLE_FONT_SIZE, LIGHTGRAY);
+            }
+            off_set_y += 30.0;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            let next = match current {
+                Some(index) => usize::min(recent_servers.len() - 1, index + 1),
+                None => 0,
+            };
+            *server_addr = recent_servers[next].clone();
+        }
+        if is_key_pressed(KeyCode::Up) {
+            let previous = match current {
+                Some(index) => index.saturating_sub(1),
+                None => 0,
+            };
+            *server_addr = recent_servers[previous].clone();
+        }
+    }
 
     if let Some(c) = get_char_pressed() {
         if c == 3 as char || c == 13 as char {
-            *status = Status::EnterName;
+            *address_error = None;
+            *pending_connection = Some(PendingConnection::start(server_addr));
             return;
         }
 
-        if is_valid_ip_char(c) {
+        if is_valid_address_char(c) {
             server_addr.push(c);
         }
     }
@@ -852,15 +822,15 @@ fn handle_ip_input(status: &mut Status, server_addr: &mut String) {
         exit(0);
     }
 }
-fn handle_name_input(status: &mut Status, player_name: &mut String, server_addr: &String) {
+fn handle_name_input(status: &mut Status, player_name: &mut String, server_addr: &str) {
     clear_background(BLACK);
 
     let mut server_addr_display =
-        "Enter server IP address. Example: 127.0.0.1:4000    ".to_string();
+        "Enter server address. Example: 127.0.0.1:4000, localhost or [::1]:4000    ".to_string();
     server_addr_display.push_str(server_addr);
 
     let mut player_name_display = "Enter your name:     ".to_string();
-    player_name_display.push_str(&player_name);
+    player_name_display.push_str(player_name);
 
     draw_text(
         server_addr_display.as_str(),
@@ -878,11 +848,9 @@ fn handle_name_input(status: &mut Status, player_name: &mut String, server_addr:
     );
 
     if let Some(c) = get_char_pressed() {
-        if c == 3 as char || c == 13 as char {
-            if player_name.len() > 2 {
-                *status = Status::SelectMap;
-                return;
-            }
+        if (c == 3 as char || c == 13 as char) && player_name.len() > 2 {
+            *status = Status::SelectTeam;
+            return;
         }
         if player_name.len() < MAX_NAME_LENGTH && is_valid_name_char(c) {
             player_name.push(c);
@@ -897,6 +865,56 @@ fn handle_name_input(status: &mut Status, player_name: &mut String, server_addr:
         exit(0);
     }
 }
+fn select_team_handler(status: &mut Status, team: &mut Option<Team>, selected_index: &mut usize) {
+    clear_background(BLACK);
+    draw_text(
+        "Select team. Enter: confirm",
+        10.0,
+        20.0,
+        CONSOLE_FONT_SIZE,
+        LIGHTGRAY,
+    );
+    //first entry plays without a team
+    let mut choices: Vec<Option<Team>> = vec![None];
+    choices.extend(Team::ALL.iter().map(|team| Some(*team)));
+
+    let mut off_set_y = 60.0;
+    for (index, choice) in choices.iter().enumerate() {
+        let (text, color) = match choice {
+            Some(team) => (team.name(), team.color()),
+            None => ("No team", LIGHTGRAY),
+        };
+        if index == *selected_index {
+            draw_rectangle(
+                0.0,
+                off_set_y - 5.0 - 12.0,
+                screen_width(),
+                CONSOLE_FONT_SIZE + 5.0,
+                color,
+            );
+            draw_text(text, 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
+        } else {
+            draw_text(text, 10.0, off_set_y, CONSOLE_FONT_SIZE, color);
+        }
+        off_set_y += 30.0;
+    }
+
+    if is_key_pressed(KeyCode::Down) {
+        *selected_index = usize::min(choices.len() - 1, *selected_index + 1);
+    }
+    if is_key_pressed(KeyCode::Up) {
+        *selected_index = selected_index.saturating_sub(1);
+    }
+    if let Some(c) = get_char_pressed() {
+        if c == 3 as char || c == 13 as char {
+            *team = choices[*selected_index];
+            *status = Status::SelectMap;
+        }
+    }
+    if is_key_pressed(KeyCode::Escape) {
+        exit(0);
+    }
+}
 fn select_map_handler(
     status: &mut Status,
     map_path: &mut String,
@@ -904,78 +922,64 @@ fn select_map_handler(
     player_name: &String,
     selected_path_index: &mut i32,
 ) {
-    if let Ok(paths) = fs::read_dir(MAPS_DIRECTORY_PATH) {
-        let mut map_paths = vec![];
-        for path in paths {
-            if let Ok(_path) = path {
-                let path_as_str = format!("{:?}", _path.path().display());
-                if !path_as_str.contains("/.") {
-                    map_paths.push(_path.path());
-                }
-            }
-        }
-        if map_paths.len() == 0 {
-            *status = Status::Init;
-            return;
-        }
-
-        draw_text(
-            format!(
-                "Enter server IP address. Example: 127.0.0.1:4000    {}",
-                server_addr
-            )
-            .as_str(),
-            10.0,
-            20.0,
-            CONSOLE_FONT_SIZE,
-            LIGHTGRAY,
-        );
-        draw_text(
-            format!("Enter your name:     {}", player_name).as_str(),
-            10.0,
-            40.0,
-            CONSOLE_FONT_SIZE,
-            LIGHTGRAY,
-        );
+    let map_paths = list_maps(MAPS_DIRECTORY_PATH);
+    if map_paths.is_empty() {
+        *status = Status::Init;
+        return;
+    }
 
-        let mut off_set_y = 70.0;
-        for (index, path) in map_paths.iter().enumerate() {
-            let mut text = format!("{:?}", path.display());
-            text = text.replace('"', "");
-            if index as i32 == *selected_path_index {
-                draw_rectangle(
-                    0.0,
-                    off_set_y - 5.0 - 12.0,
-                    screen_width(),
-                    CONSOLE_FONT_SIZE + 5.0,
-                    LIGHTGRAY,
-                );
-                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
-            } else {
-                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, LIGHTGRAY);
-            }
-            off_set_y += 30.0;
-        }
+    draw_text(
+        format!(
+            "Enter server IP address. Example: 127.0.0.1:4000    {}",
+            server_addr
+        )
+        .as_str(),
+        10.0,
+        20.0,
+        CONSOLE_FONT_SIZE,
+        LIGHTGRAY,
+    );
+    draw_text(
+        format!("Enter your name:     {}", player_name).as_str(),
+        10.0,
+        40.0,
+        CONSOLE_FONT_SIZE,
+        LIGHTGRAY,
+    );
 
-        if is_key_pressed(KeyCode::Down) {
-            *selected_path_index =
-                i32::min(map_paths.len() as i32 - 1, selected_path_index.clone() + 1);
-        }
-        if is_key_pressed(KeyCode::Up) {
-            *selected_path_index = i32::max(0, selected_path_index.clone() - 1);
+    let mut off_set_y = 70.0;
+    for (index, path) in map_paths.iter().enumerate() {
+        let text = path;
+        if index as i32 == *selected_path_index {
+            draw_rectangle(
+                0.0,
+                off_set_y - 5.0 - 12.0,
+                screen_width(),
+                CONSOLE_FONT_SIZE + 5.0,
+                LIGHTGRAY,
+            );
+            draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
+        } else {
+            draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, LIGHTGRAY);
         }
+        off_set_y += 30.0;
+    }
 
-        if let Some(c) = get_char_pressed() {
-            if c == 3 as char || c == 13 as char {
-                *map_path = format!("{}", map_paths[*selected_path_index as usize].display());
-                *status = Status::Init;
-            }
-        }
-        if is_key_pressed(KeyCode::Escape) {
-            exit(0);
+    if is_key_pressed(KeyCode::Down) {
+        *selected_path_index = i32::min(map_paths.len() as i32 - 1, *selected_path_index + 1);
+    }
+    if is_key_pressed(KeyCode::Up) {
+        *selected_path_index = i32::max(0, *selected_path_index - 1);
+    }
+
+    if let Some(c) = get_char_pressed() {
+        if c == 3 as char || c == 13 as char {
+            *map_path = map_paths[*selected_path_index as usize].clone();
+            *status = Status::Init;
         }
-    } else {
-        *status = Status::Init;
+    }
+    if is_key_pressed(KeyCode::Escape) {
+        exit(0);
     }
 }
 fn init_game_handler(
@@ -984,26 +988,36 @@ fn init_game_handler(
     player: &mut Option<Arc<Mutex<Player>>>,
     player_name: &String,
     map_path: &String,
+    team: Option<Team>,
+    args: &Args,
 ) {
-    let params = init_game_params(map_path);
+    let mut params = init_game_params(map_path);
+    if args.bot {
+        params.bot = Some(Bot::default());
+    }
     *game_params = Some(params.clone());
-    let _player = init_player(&params, player_name, map_path);
+    let mut _player = init_player(&params, player_name, map_path);
+    _player.spectator = args.spectate;
+    _player.team = team;
     *player = Some(Arc::new(Mutex::new(_player)));
     *status = Status::StartServerListener;
 }
+#[allow(clippy::too_many_arguments)]
 fn handle_game_run(
-    server_addr: &String,
+    server_addr: SocketAddr,
     player_ref: Arc<Mutex<Player>>,
     game_params: &mut GameParams,
     socket: &Arc<UdpSocket>,
     enemies: Arc<Mutex<Option<Vec<Player>>>>,
+    match_status: Arc<Mutex<MatchStatus>>,
     is_first_tun: &mut bool,
     fps: f32,
     font: &Font,
-    move_speed: f32,
-    look_speed: f32,
+    settings: &mut Settings,
     grabbed: &mut bool,
+    audio: &mut Audio,
 ) {
+    let move_speed = settings.move_speed;
     let mut require_update = false;
     if *is_first_tun {
         *is_first_tun = false;
@@ -1011,59 +1025,229 @@ fn handle_game_run(
     }
 
     let delta = get_frame_time();
+    update_layout(game_params);
+    let layout = game_params.layout;
 
     match player_ref.lock() {
         Ok(mut player) => {
-            let prev_pos = player.position_vec3.clone();
+            let prev_pos = player.position_vec3;
             let front = player.front;
             let right = player.right;
 
-            if is_key_pressed(KeyCode::Escape) {
+            //keys closing an overlay must not act on the game in the same frame
+            let overlay_was_open =
+                game_params.rebind_menu.is_some() || game_params.chat.is_typing();
+
+            //chat input suspends the controls while typing
+            if let Some(text) = game_params.chat.update() {
+                if let Ok(mut log) = game_params.chat.log.lock() {
+                    log.push(&player.name, &text);
+                }
+                let request = ChatRequest {
+                    chat: ChatMessage {
+                        sender_id: player.id.clone(),
+                        name: player.name.clone(),
+                        text,
+                    },
+                };
+                send_chat_to_server(socket, server_addr, &request);
+            }
+
+            //key bindings screen takes over the input until it is closed
+            if let Some(menu) = game_params.rebind_menu.as_mut() {
+                if !menu.update(&mut settings.key_bindings) {
+                    game_params.rebind_menu = None;
+                    //a settings file that failed to load is not overwritten
+                    if settings.load_error.is_none() {
+                        if let Err(e) = settings.save() {
+                            println!("Error while saving settings: {}", e);
+                        }
+                    }
+                }
+            } else if !game_params.chat.is_typing()
+                && settings.key_bindings.is_pressed(Action::KeyBindings)
+            {
+                game_params.rebind_menu = Some(RebindMenu::default());
+            }
+            let controls_active = !overlay_was_open
+                && game_params.rebind_menu.is_none()
+                && !game_params.chat.is_typing();
+
+            if controls_active
+                && game_params.bot.is_none()
+                && settings.key_bindings.is_pressed(Action::Chat)
+            {
+                game_params.chat.open();
+            }
+
+            if controls_active && settings.key_bindings.is_pressed(Action::Quit) {
                 player.player_status = PlayerStatus::Disconnent;
                 send_message_to_server(socket, server_addr, &player, &player.id);
                 exit(0);
             }
 
-            if is_key_pressed(KeyCode::Tab) {
+            if controls_active && settings.key_bindings.is_pressed(Action::ToggleGrab) {
                 *grabbed = !*grabbed;
                 set_cursor_grab(*grabbed);
                 show_mouse(!*grabbed);
             }
 
-            if is_key_down(KeyCode::Up) || is_key_down(KeyCode::W) {
+            let mouse_position: Vec2 = mouse_position().into();
+            let mouse_delta = mouse_position - game_params.last_mouse_position;
+            game_params.last_mouse_position = mouse_position;
+
+            let mut controls = if !controls_active {
+                Controls::default()
+            } else if let Some(bot) = game_params.bot.as_mut() {
+                let visible_enemies: Vec<Player> = match enemies.lock() {
+                    Ok(enemies_locked) => enemies_locked
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|enemy| !player.is_teammate(enemy))
+                        .collect(),
+                    Err(_) => vec![],
+                };
+                match game_params.hittables.lock() {
+                    Ok(hittables) => bot.controls(&player, &visible_enemies, &hittables, delta),
+                    Err(_) => Controls::default(),
+                }
+            } else {
+                Controls::from_input(
+                    &settings.key_bindings,
+                    mouse_delta,
+                    delta * settings.look_speed,
+                    settings.invert_y,
+                )
+            };
+            //spectators only watch
+            if player.spectator {
+                controls.fire = false;
+            }
+
+            let now = date::now();
+            let match_state = match match_status.lock() {
+                Ok(status) => status.clone(),
+                Err(_) => MatchStatus::default(),
+            };
+            //the server restarted the round, everybody starts again from zero
+            if match_state.state.round != game_params.round {
+                game_params.round = match_state.state.round;
+                respawn_player(&mut player, now, match_state.options.max_health);
+                game_params.weapons = Weapons::default();
+                player.score = 0;
+                player.kills = 0;
+                player.deaths = 0;
+                player.captures = 0;
+                player.carrying_flag = None;
+                player.dropped_flag = None;
+                require_update = true;
+            }
+            if match_state.state.phase == MatchPhase::Intermission {
+                controls = Controls::default();
+            }
+            if player.health == 0 && player.death_time.is_none() && !player.spectator {
+                player.player_status = PlayerStatus::Killed;
+                player.deaths += 1;
+                player.death_time = Some(now);
+                audio.play(SoundEffect::Death, 1.0, 0.0, settings);
+                require_update = true;
+            }
+            if let Some(death_time) = player.death_time {
+                controls = Controls::default();
+                if match_state.allows_respawn()
+                    && now - death_time >= match_state.options.respawn_delay as f64
+                {
+                    respawn_player(&mut player, now, match_state.options.max_health);
+                    game_params.weapons = Weapons::default();
+                    require_update = true;
+                }
+            } else if player.health > match_state.options.max_health {
+                player.health = match_state.options.max_health;
+                require_update = true;
+            } else if player.health < match_state.options.max_health
+                && match_state.options.health_regen > 0
+                && now >= player.health_regen_at
+            {
+                player.health = u32::min(
+                    player.health + match_state.options.health_regen,
+                    match_state.options.max_health,
+                );
+                player.health_regen_at = now + 1.0;
+                require_update = true;
+            }
+            let flags = if match_state.options.mode == GameMode::CaptureTheFlag {
+                match game_params.roster.lock() {
+                    Ok(roster) => flags(
+                        &game_params.flag_bases,
+                        &with_local_player(&roster, &player),
+                    ),
+                    Err(_) => vec![],
+                }
+            } else {
+                vec![]
+            };
+            if update_flags(&mut player, &flags, now) {
+                require_update = true;
+            }
+            //the answer is handled by the listener, the new ping goes out with the next update
+            if now - game_params.last_ping_query > PING_INTERVAL {
+                game_params.last_ping_query = now;
+                player.ping_query_time = Some(now);
+                send_status_query(socket, server_addr);
+                require_update = true;
+            }
+            if controls.reload {
+                game_params.weapons.reload(now);
+            }
+            if controls.next_weapon {
+                game_params.weapons.select_next(now);
+            }
+            if let Some(index) = controls.select_weapon {
+                game_params.weapons.select(index, now);
+            }
+            game_params.weapons.update(now);
+            let fired = controls.fire && game_params.weapons.fire(now);
+            if fired {
+                audio.play(SoundEffect::Fire, 1.0, 0.0, settings);
+            }
+            //protection ends early once the player fires
+            if player.spawn_protected && (now > player.spawn_protected_until || fired) {
+                player.spawn_protected = false;
+                require_update = true;
+            }
+
+            if controls.move_forward {
                 player.position_vec3 += front * move_speed;
                 require_update = true;
             }
-            if is_key_down(KeyCode::Down) || is_key_down(KeyCode::S) {
+            if controls.move_backward {
                 player.position_vec3 -= front * move_speed;
                 require_update = true;
             }
-            if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
+            if controls.strafe_left {
                 player.position_vec3 -= right * move_speed;
                 require_update = true;
             }
-            if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
+            if controls.strafe_right {
                 player.position_vec3 += right * move_speed;
                 require_update = true;
             }
 
-            let gap: f32 = 0.05;
+            //the eyeball stays out of the walls, other players see it and shoot at it
+            let gap: f32 = AVATAR_RADIUS;
             handle_wall_collisions(
                 &game_params.mini_map,
                 prev_pos,
                 &mut player.position_vec3,
                 gap,
             );
-            let mouse_position: Vec2 = mouse_position().into();
-            let mouse_delta = mouse_position - game_params.last_mouse_position;
-            //let mouse_delta_length = mouse_delta.length();
-            if mouse_delta.length() > 0.0 {
+            if controls.look.length() > 0.0 {
                 require_update = true;
             }
-            game_params.last_mouse_position = mouse_position;
 
-            player.yaw += mouse_delta.x * delta * look_speed;
-            player.pitch += mouse_delta.y * delta * -look_speed;
+            player.yaw += controls.look.x;
+            player.pitch += controls.look.y;
             player.pitch = if player.pitch > MAX_PITCH {
                 MAX_PITCH
             } else {
@@ -1093,10 +1277,10 @@ fn handle_game_run(
             player.orientation =
                 orientaion_to_degrees(vec3(orientation.x, orientation.y, orientation.z));
             draw_rectangle_lines(
-                MAP_MARGIN_LEFT,
-                MAP_MARGIN_TOP,
-                MAP_WIDTH,
-                MAP_HEIGHT,
+                layout.mini_map.x,
+                layout.mini_map.y,
+                layout.mini_map.w,
+                layout.mini_map.h,
                 2.0,
                 DARKGRAY,
             );
@@ -1104,21 +1288,21 @@ fn handle_game_run(
             let params = TextParams {
                 font: Some(font),
                 font_size: GAME_FONT_SIZE,
-                font_scale: 1.0,
+                font_scale: layout.scale,
                 font_scale_aspect: 1.0,
                 rotation: 0.0,
                 color: BLACK,
             };
             draw_text_ex(
                 &player.name,
-                NAME_MARGIN_LEFT,
-                NAME_MARGIN_TOP,
+                layout.scoreboard.x,
+                layout.scoreboard.y,
                 params.clone(),
             );
             draw_text_ex(
                 format!("{}", player.score).as_str(),
-                SCORE_MARGIN_LEFT,
-                NAME_MARGIN_TOP,
+                layout.score_column,
+                layout.scoreboard.y,
                 params,
             );
 
@@ -1138,30 +1322,48 @@ fn handle_game_run(
             );
             draw_texture_ex(
                 &game_params.render_target.texture,
-                MAIN_MARGIN_LEFT,
-                MAIN_MARGIN_TOP + MAIN_HEIGHT,
+                layout.viewport.x,
+                layout.viewport.y + layout.viewport.h,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(Vec2::new(MAIN_WIDTH, -MAIN_HEIGHT)),
+                    dest_size: Some(Vec2::new(layout.viewport.w, -layout.viewport.h)),
                     ..Default::default()
                 },
             );
 
+            draw_flags_on_minimap(&flags, &game_params.mini_map, &game_params.mini_map_config);
+
             //enemies
             if let Ok(enemies_result) = enemies.lock() {
                 if let Some(enemies) = enemies_result.clone() {
-                    draw_enemy_names_and_scores(&enemies, font);
-                    //draw_enemies_on_minimap(&enemies, &game_params);
+                    draw_enemy_names_and_scores(&enemies, font, &layout);
+                    track_enemy_hits(&enemies, &mut game_params.enemy_hits);
+                    for position in track_enemy_shots(
+                        &enemies,
+                        &mut game_params.enemy_shots,
+                        &mut game_params.shots,
+                    ) {
+                        audio.play_at(SoundEffect::Fire, position, &player, settings);
+                    }
+                    match match_status.lock() {
+                        Ok(status) => {
+                            draw_enemies_on_minimap(&enemies, &player, game_params, &status.options)
+                        }
+                        Err(e) => println!("Error while locking match status: {:?}", e),
+                    }
                 }
             }
 
-            set_camera(&Camera3D {
+            let camera = Camera3D {
                 render_target: Some(game_params.render_target.clone()),
+                aspect: Some(layout.viewport.w / layout.viewport.h),
+                fovy: settings.fov.to_radians(),
                 position: player.position_vec3,
-                up: up,
+                up,
                 target: player.position_vec3 + player.front,
                 ..Default::default()
-            });
+            };
+            set_camera(&camera);
 
             clear_background(LIGHTGRAY);
             draw_walls(
@@ -1218,127 +1420,216 @@ fn handle_game_run(
             if let Ok(enemies_result) = enemies.lock() {
                 if let Some(enemies) = enemies_result.clone() {
                     for enemy in enemies {
-                        let bytes = game_params.eye_texture.bytes.clone();
-                        let width = game_params.eye_texture.width as u32;
-                        let height = game_params.eye_texture.height;
-
-                        let a = enemy.orientation.to_degrees() as u32;
-                        let index = a * width * 4;
-                        let mut top_half = bytes[..index as usize].to_vec();
-                        let mut bottom_half = bytes[index as usize..].to_vec();
-                        let mut bytes = vec![];
-                        bytes.append(&mut bottom_half);
-                        bytes.append(&mut top_half);
-                        let image = Image {
-                            bytes,
-                            width: width as u16,
-                            height,
-                        };
-                        let texture = Texture2D::from_image(&image);
-                        draw_sphere(
-                            vec3(enemy.position.x, PLAYER_HEIGHT, enemy.position.z),
-                            ENEMY_RADIUS,
-                            Some(&texture),
-                            WHITE,
-                        );
+                        let hit_flash = game_params
+                            .enemy_hits
+                            .get(&enemy.id)
+                            .is_some_and(|(_, hit_at)| get_time() - hit_at < HIT_FLASH_DURATION);
+                        draw_avatar(&enemy, hit_flash);
+                        //muzzle flash in front of the eye right after a shot
+                        let just_fired =
+                            game_params
+                                .enemy_shots
+                                .get(&enemy.id)
+                                .is_some_and(|(_, fired_at)| {
+                                    get_time() - fired_at < MUZZLE_FLASH_DURATION
+                                });
+                        if let (true, Some(muzzle)) = (just_fired, muzzle_position(&enemy)) {
+                            draw_sphere(
+                                muzzle,
+                                AVATAR_RADIUS / 3.0,
+                                None,
+                                Color::new(1.0, 0.9, 0.4, 0.9),
+                            );
+                        }
                     }
                 }
             }
 
+            draw_flags(&flags, &player.id);
+
             //shooting
-            if is_mouse_button_pressed(MouseButton::Left) {
+            if fired {
                 match game_params.hittables.lock() {
                     Ok(mut hittables) => {
-                        let mut closest_hit_option: Option<Hit> = None;
-
-                        let start =
-                            vec3(player.position.x, 0.95, player.position.z) + player.front / 10.0;
-
-                        for hittable in hittables.iter() {
-                            if let Hittable::Wall(shield) = hittable {
-                                let hit_option = shield.hit(start, player.front);
-                                if let Some(hit) = hit_option {
-                                    if let Some(ref closest_hit) = closest_hit_option {
-                                        if hit.t < closest_hit.t {
-                                            closest_hit_option = Some(hit);
-                                        }
-                                    } else {
-                                        closest_hit_option = Some(hit);
-                                    }
+                        let weapon = game_params.weapons.selected().kind;
+                        let spec = weapon.spec();
+                        let damage = spec.damage(match_state.options.shot_damage);
+                        /*
+                            rays and projectiles start at the eye, which the collision gap keeps out of the walls,
+                            so a wall right in front of the player is never skipped. Only the shot line starts
+                            in front of the eyeball
+                        */
+                        let eye = player.position_vec3;
+                        let muzzle = vec3(player.position.x, 0.95, player.position.z)
+                            + player.front * MUZZLE_OFFSET;
+                        player.tracers.clear();
+                        for (index, direction) in spec
+                            .directions(player.front, player.right)
+                            .into_iter()
+                            .enumerate()
+                        {
+                            if spec.projectile.is_some() {
+                                let launch = ProjectileLaunch {
+                                    id: player.shots_fired * spec.rays + index as u32,
+                                    weapon,
+                                    origin: eye.to_array(),
+                                    direction: direction.to_array(),
                                 };
-                            }
-                            //implement for enemy
-                            if let Hittable::Enemy(enemy) = hittable {
-                                let hit_option = enemy.hit(start, player.front);
-                                if let Some(hit) = hit_option {
-                                    if let Some(ref closest_hit) = closest_hit_option {
-                                        if hit.t < closest_hit.t {
-                                            closest_hit_option = Some(hit);
-                                        }
-                                    } else {
-                                        closest_hit_option = Some(hit);
-                                    }
+                                if let Some(projectile) = Projectile::launch(None, &launch, damage)
+                                {
+                                    game_params.projectiles.push(projectile);
                                 }
+                                player.projectiles.push(launch);
+                                continue;
                             }
-                        }
-
-                        let end = if let Some(closest_hit) = closest_hit_option {
-                            match closest_hit.hittable {
-                                Hittable::Wall(_) => {}
-                                Hittable::Enemy(mut enemy) => {
-                                    //hit enemy
-                                    //update score
-                                    if let PlayerStatus::Active = enemy.player_status {
-                                        player.score = player.score + 1;
-                                        require_update = true;
+                            let end = match closest_hit(&hittables, eye, direction, None)
+                                .filter(|hit| hit.t <= spec.range)
+                            {
+                                Some(hit) => {
+                                    let p = hit.p;
+                                    if let Hittable::Wall(ref shield) = hit.hittable {
+                                        game_params
+                                            .effects
+                                            .wall_impact(p, shield, direction, spec.color);
                                     }
-                                    enemy.player_status = PlayerStatus::Killed;
-
-                                    //remove from hitables
-                                    let _hittables: Vec<Hittable> = hittables
-                                        .iter()
-                                        .filter(|hittable| {
-                                            if let Hittable::Enemy(_enemy) = hittable {
-                                                _enemy.id != enemy.id
-                                            } else {
-                                                true
-                                            }
-                                        })
-                                        .cloned()
-                                        .collect();
-                                    *hittables = _hittables;
-
-                                    //notify server
-                                    send_message_to_server(
+                                    if hit_enemy(
+                                        hit.hittable,
+                                        damage,
+                                        &mut player,
+                                        match_state.options.friendly_fire,
+                                        &mut hittables,
                                         socket,
                                         server_addr,
-                                        &enemy,
-                                        &player.id,
-                                    );
+                                    ) {
+                                        audio.play(SoundEffect::Hit, 1.0, 0.0, settings);
+                                    }
+                                    p
                                 }
-                            }
-                            closest_hit.p
-                        } else {
-                            start + player.front * MAX_SHOT_RANGE
-                        };
-
-                        let shot = Shot {
-                            start,
-                            end,
-                            time_out: SHOT_DURATION,
-                            color: YELLOW,
-                        };
-                        game_params.shots.push(shot);
+                                None => eye + direction * spec.range,
+                            };
+                            game_params.shots.push(Shot {
+                                start: muzzle,
+                                end,
+                                time_out: SHOT_DURATION,
+                                color: spec.color,
+                            });
+                            player.tracers.push(Tracer {
+                                weapon,
+                                start: muzzle.to_array(),
+                                end: end.to_array(),
+                            });
+                        }
+                        //let other clients know that this player has fired
+                        player.shots_fired += 1;
+                        require_update = true;
                     }
                     Err(e) => println!("Error while locking hittables {:?}", e),
                 }
             }
+            match game_params.roster.lock() {
+                Ok(roster) => sync_remote_projectiles(
+                    &mut game_params.projectiles,
+                    &mut game_params.seen_launches,
+                    &roster,
+                    &player.id,
+                ),
+                Err(e) => println!("Error while locking roster: {:?}", e),
+            }
+            match game_params.hittables.lock() {
+                Ok(mut hittables) => {
+                    let (ended, enemy_hit) = update_projectiles(
+                        &mut game_params.projectiles,
+                        delta,
+                        &mut player,
+                        match_state.options.friendly_fire,
+                        &mut hittables,
+                        &mut game_params.effects,
+                        socket,
+                        server_addr,
+                    );
+                    if enemy_hit {
+                        audio.play(SoundEffect::Hit, 1.0, 0.0, settings);
+                    }
+                    //the other clients remove the projectile once it is gone from the record
+                    if !ended.is_empty() {
+                        player
+                            .projectiles
+                            .retain(|launch| !ended.contains(&launch.id));
+                        require_update = true;
+                    }
+                }
+                Err(e) => println!("Error while locking hittables {:?}", e),
+            }
+            draw_projectiles(&game_params.projectiles);
+            for impact in game_params.effects.take_impacts() {
+                audio.play_at(SoundEffect::Impact, impact, &player, settings);
+            }
+            match game_params.roster.lock() {
+                Ok(roster) => {
+                    let walking: Vec<&Player> = with_local_player(&roster, &player)
+                        .into_iter()
+                        .filter(|_player| {
+                            !_player.spectator
+                                && matches!(_player.player_status, PlayerStatus::Active)
+                        })
+                        .collect();
+                    audio.footsteps(&walking, &player, settings);
+                }
+                Err(e) => println!("Error while locking roster: {:?}", e),
+            }
+            game_params.effects.update(delta);
+            game_params.effects.draw();
 
             draw_shots(&game_params.shots);
             remove_shots(&mut game_params.shots);
 
+            set_default_camera();
+            if let (Ok(enemies_result), Ok(hittables)) =
+                (enemies.lock(), game_params.hittables.lock())
+            {
+                if let Some(enemies) = enemies_result.as_ref() {
+                    draw_nameplates(enemies, &camera, &hittables, font, &layout);
+                }
+            }
+            if let Some(death_time) = player.death_time {
+                let respawn_delay = if match_state.allows_respawn() {
+                    Some(match_state.options.respawn_delay)
+                } else {
+                    None
+                };
+                draw_death_screen(&player, death_time, respawn_delay, font, &layout);
+            }
+            if player.death_time.is_none() && !player.spectator {
+                draw_health_bar(player.health, match_state.options.max_health, font, &layout);
+                game_params.weapons.draw(font, &layout);
+            }
+            game_params.chat.draw(font, &layout);
+            match game_params.roster.lock() {
+                Ok(roster) => {
+                    if match_state.state.phase == MatchPhase::Intermission {
+                        draw_winner_screen(&match_state, &roster, &player, font, &layout, now);
+                    } else {
+                        draw_match_hud(&match_state, &roster, &player, font, &layout, now);
+                        if controls_active && settings.key_bindings.is_down(Action::Scoreboard) {
+                            draw_scoreboard(&roster, &player, font, &layout);
+                        }
+                    }
+                }
+                Err(e) => println!("Error while locking roster: {:?}", e),
+            }
+            match game_params.feed.lock() {
+                Ok(feed) => feed.draw(font, &layout),
+                Err(e) => println!("Error while locking kill feed: {:?}", e),
+            }
+
+            if let Some(ref menu) = game_params.rebind_menu {
+                menu.draw(&settings.key_bindings);
+            }
+
             if require_update {
                 send_message_to_server(socket, server_addr, &player, &player.id);
+                //rays of a shot go out with one update only, other clients notice the shot by shots_fired
+                player.tracers.clear();
             }
         }
         Err(e) => {
@@ -1346,17 +1637,23 @@ fn handle_game_run(
         }
     }
 }
+#[allow(clippy::too_many_arguments)]
 fn start_server_listener(
     socket: Arc<UdpSocket>,
     enemies: Arc<Mutex<Option<Vec<Player>>>>,
     player: Arc<Mutex<Player>>,
     hittables: Arc<Mutex<Vec<Hittable>>>,
-    server_addr: String
+    match_status: Arc<Mutex<MatchStatus>>,
+    chat_log: Arc<Mutex<ChatLog>>,
+    feed: Arc<Mutex<KillFeed>>,
+    roster: Arc<Mutex<Vec<Player>>>,
 ) {
     let player_id = player.lock().unwrap().id.clone();
+    //player records carry their projectiles, the update of a full server does not fit in a small buffer.
+    //allocated once and reused for every packet
+    let mut buffer = vec![0u8; MAX_PACKET_SIZE];
     //Server response listener
     thread::spawn(move || loop {
-        let mut buffer = [0u8; 2048];
         if let Ok((size, _)) = socket.recv_from(&mut buffer) {
             // println!(
             //     "Received {} bytes from {}: {}",
@@ -1366,79 +1663,109 @@ fn start_server_listener(
             // );
 
             if let Ok(players_str) = std::str::from_utf8(&buffer[..size]) {
-                match from_str::<Vec<Player>>(players_str) {
-                    Ok(players) => {
+                match from_str::<ServerUpdate>(players_str) {
+                    Ok(update) => {
+                        let players = match update {
+                            ServerUpdate::Chat { chat } => {
+                                receive_chat(&chat_log, &chat, &player_id);
+                                continue;
+                            }
+                            ServerUpdate::Match {
+                                players,
+                                options,
+                                state,
+                            } => {
+                                match match_status.lock() {
+                                    Ok(mut match_status_locked) => {
+                                        *match_status_locked = MatchStatus {
+                                            options,
+                                            state,
+                                            received_at: date::now(),
+                                        }
+                                    }
+                                    Err(e) => {
+                                        println!("Error while locking match status: {:?}", e)
+                                    }
+                                }
+                                players
+                            }
+                            ServerUpdate::Players(players) => players,
+                            ServerUpdate::Status(_) => {
+                                match player.lock() {
+                                    Ok(mut player_locked) => {
+                                        if let Some(sent) = player_locked.ping_query_time.take() {
+                                            player_locked.ping =
+                                                ((date::now() - sent) * 1000.0) as u32;
+                                        }
+                                    }
+                                    Err(e) => println!("Error while locking player: {:?}", e),
+                                }
+                                continue;
+                            }
+                        };
+                        match roster.lock() {
+                            Ok(mut roster_locked) => *roster_locked = players.clone(),
+                            Err(e) => println!("Error while locking roster: {:?}", e),
+                        }
+                        match feed.lock() {
+                            Ok(mut feed_locked) => feed_locked.update(&players, &player_id),
+                            Err(e) => println!("Error while locking kill feed: {:?}", e),
+                        }
                         //clear hittables from enemies
                         match hittables.lock() {
                             Ok(mut hittables_locked) => {
                                 *hittables_locked = hittables_locked
                                     .iter()
-                                    .filter(|item| {
-                                        if let Hittable::Enemy(_) = item {
-                                            false
-                                        } else {
-                                            true
-                                        }
-                                    })
+                                    .filter(|item| !matches!(item, Hittable::Enemy(_)))
                                     .cloned()
                                     .collect();
                             }
                             Err(e) => println!("Error while locking hittables {:?}", e),
                         }
 
+                        let names: HashMap<String, String> = players
+                            .iter()
+                            .map(|_player| (_player.id.clone(), _player.name.clone()))
+                            .collect();
+
                         //filter player and handle if killed
                         let mut enemies_local_option: Option<Vec<Player>> = None;
                         for _player in players {
                             if _player.id == player_id {
-                                if let PlayerStatus::Killed = _player.player_status {
-                                    //player is killed. update position and status
-                                    match player.lock() {
-                                        Ok(mut player_locked) => {
-                                            println!("Player {} killed", player_locked.name);
-                                            let position =
-                                                generate_position(&player_locked.mini_map);
-                                            player_locked.position_vec3 = position;
-                                            player_locked.position =
-                                                Position::build(position.x, position.z);
-                                            player_locked.player_status = PlayerStatus::Active;
-                                            //send message to server
-                                            let server_object = ServerMessage {
-                                                sender_id: player_locked.id.clone(),
-                                                player: player_locked.clone(),
-                                            };
-                                            if let Ok(message_to_server) =
-                                                serde_json::to_string(&server_object)
-                                            {
-                                                if let Err(e) = socket.send_to(
-                                                    message_to_server.as_bytes(),
-                                                    server_addr.clone(),
-                                                ) {
-                                                    println!(
-                                                        "Error while sending message {} to server: {:?}",
-                                                        message_to_server, e
-                                                    );
-                                                }
-                                            }
+                                //hits by other players, the game loop shows the death screen once health is gone
+                                match player.lock() {
+                                    Ok(mut player_locked) => {
+                                        if player_locked.apply_remote_damage(&_player, date::now())
+                                        {
+                                            player_locked.killer_name = _player
+                                                .killed_by
+                                                .as_ref()
+                                                .and_then(|id| names.get(id))
+                                                .cloned();
                                         }
-                                        Err(e) => println!("Error while locking player: {:?}", e),
                                     }
+                                    Err(e) => println!("Error while locking player: {:?}", e),
                                 }
-                            } else {
-                                //collect enemies
+                            } else if !_player.spectator {
+                                //killed players wait for their respawn and can not be hit
                                 if let PlayerStatus::Active = _player.player_status {
+                                    //collect enemies
                                     if let Some(ref mut enemies_local) = enemies_local_option {
                                         enemies_local.push(_player.clone());
                                     } else {
                                         enemies_local_option = Some(vec![_player.clone()]);
                                     }
-                                }
 
-                                //update hittables
-                                match hittables.lock() {
-                                    Ok(mut hittables_locked) => {
-                                        hittables_locked.push(Hittable::Enemy(_player));
+                                    //update hittables
+                                    match hittables.lock() {
+                                        Ok(mut hittables_locked) => {
+                                            hittables_locked
+                                                .push(Hittable::Enemy(Box::new(_player)));
+                                        }
+                                        Err(e) => {
+                                            println!("Error while locking hittables {:?}", e)
+                                        }
                                     }
-                                    Err(e) => println!("Error while locking hittables {:?}", e),
                                 }
                             }
                         }
@@ -1461,13 +1788,7 @@ fn start_server_listener(
                     Ok(mut hittables_locked) => {
                         *hittables_locked = hittables_locked
                             .iter()
-                            .filter(|item| {
-                                if let Hittable::Enemy(_) = item {
-                                    false
-                                } else {
-                                    true
-                                }
-                            })
+                            .filter(|item| !matches!(item, Hittable::Enemy(_)))
                             .cloned()
                             .collect();
                     }
@@ -1477,59 +1798,473 @@ fn start_server_listener(
         }
     });
 }
-fn draw_enemy_names_and_scores(_enemies: &Vec<Player>, font: &Font) {
-    let mut top_offset = NAME_MARGIN_TOP + 25.0;
+fn respawn_player(player: &mut Player, now: f64, max_health: u32) {
+    let position = generate_position(&player.mini_map);
+    player.position_vec3 = position;
+    player.position = Position::build(position.x, position.z);
+    player.player_status = PlayerStatus::Active;
+    player.health = max_health;
+    player.death_time = None;
+    player.killed_by = None;
+    player.killer_name = None;
+    player.spawn_protected = true;
+    player.spawn_protected_until = now + SPAWN_PROTECTION_DURATION;
+}
+//bottom left of the 3d view, turns from green to red while health goes down
+fn draw_health_bar(health: u32, max_health: u32, font: &Font, layout: &Layout) {
+    let fraction = health as f32 / u32::max(1, max_health) as f32;
+    let width = HEALTH_BAR_WIDTH * layout.scale;
+    let height = HEALTH_BAR_HEIGHT * layout.scale;
+    let left = layout.viewport.x + 6.0 * layout.scale;
+    let top = layout.viewport.y + layout.viewport.h - height - 6.0 * layout.scale;
+    draw_rectangle(left, top, width, height, Color::new(0.0, 0.0, 0.0, 0.5));
+    draw_rectangle(
+        left,
+        top,
+        width * fraction,
+        height,
+        Color::new(1.0 - fraction, fraction, 0.0, 0.9),
+    );
+    draw_rectangle_lines(left, top, width, height, 1.0, WHITE);
+    draw_text_ex(
+        format!("{}", health).as_str(),
+        left + width + 4.0 * layout.scale,
+        top + height,
+        TextParams {
+            font: Some(font),
+            font_size: GAME_FONT_SIZE,
+            font_scale: layout.scale,
+            font_scale_aspect: 1.0,
+            rotation: 0.0,
+            color: WHITE,
+        },
+    );
+}
+//fades the 3d view out and counts down to the respawn, without a delay the player waits for the next round
+fn draw_death_screen(
+    player: &Player,
+    death_time: f64,
+    respawn_delay: Option<f32>,
+    font: &Font,
+    layout: &Layout,
+) {
+    let elapsed = date::now() - death_time;
+    let fade = f64::min(1.0, elapsed / DEATH_FADE_DURATION) as f32;
+    draw_rectangle(
+        layout.viewport.x,
+        layout.viewport.y,
+        layout.viewport.w,
+        layout.viewport.h,
+        Color::new(0.2, 0.0, 0.0, 0.8 * fade),
+    );
+    let killed_by = match player.killer_name {
+        Some(ref name) => format!("Killed by {}", name),
+        None => String::from("You died"),
+    };
+    let respawn = match respawn_delay {
+        Some(delay) => format!(
+            "Respawn in {}",
+            f64::max(0.0, delay as f64 - elapsed).ceil()
+        ),
+        None => String::from("Eliminated, waiting for the next round"),
+    };
+    let center_x = layout.viewport.x + layout.viewport.w / 2.0;
+    let center_y = layout.viewport.y + layout.viewport.h / 2.0;
+    for (text, font_scale, offset_y) in [
+        (killed_by, layout.scale * 2.0, 0.0),
+        (respawn, layout.scale, layout.line_height),
+    ] {
+        let size = measure_text(text.as_str(), Some(font), GAME_FONT_SIZE, font_scale);
+        draw_text_ex(
+            text.as_str(),
+            center_x - size.width / 2.0,
+            center_y + offset_y,
+            TextParams {
+                font: Some(font),
+                font_size: GAME_FONT_SIZE,
+                font_scale,
+                font_scale_aspect: 1.0,
+                rotation: 0.0,
+                color: Color::new(1.0, 1.0, 1.0, fade),
+            },
+        );
+    }
+}
+fn draw_enemy_names_and_scores(_enemies: &[Player], font: &Font, layout: &Layout) {
+    let mut top_offset = layout.scoreboard.y + layout.line_height;
     let params = TextParams {
         font: Some(font),
         font_size: GAME_FONT_SIZE,
-        font_scale: 1.0,
+        font_scale: layout.scale,
         font_scale_aspect: 1.0,
         rotation: 0.0,
         color: BLACK,
     };
 
-    let mut enemies = _enemies.clone();
-    enemies.sort_by(|a, b| b.score.cmp(&a.score));
+    let mut enemies = _enemies.to_vec();
+    enemies.sort_by_key(|enemy| std::cmp::Reverse(enemy.score));
     if enemies.len() > 8 {
         enemies = enemies[0..8].to_vec();
     }
 
     for enemy in enemies {
         if let PlayerStatus::Active = enemy.player_status {
-            draw_text_ex(&enemy.name, NAME_MARGIN_LEFT, top_offset, params.clone());
+            draw_text_ex(&enemy.name, layout.scoreboard.x, top_offset, params.clone());
             draw_text_ex(
                 format!("{}", enemy.score).as_str(),
-                SCORE_MARGIN_LEFT,
+                layout.score_column,
                 top_offset,
                 params.clone(),
             );
-            top_offset += 25.0;
+            top_offset += layout.line_height;
         }
     }
 }
 
+fn draw_enemies_on_minimap(
+    enemies: &[Player],
+    player: &Player,
+    game_params: &GameParams,
+    options: &MatchOptions,
+) {
+    let now = get_time();
+    match game_params.hittables.lock() {
+        Ok(hittables) => {
+            for enemy in enemies {
+                if let PlayerStatus::Active = enemy.player_status {
+                    //teammates are always shown
+                    let is_visible = player.is_teammate(enemy)
+                        || match options.enemy_markers {
+                            EnemyMarkers::Always => true,
+                            EnemyMarkers::LineOfSight => is_in_line_of_sight(
+                                player.position_vec3,
+                                vec3(enemy.position.x, PLAYER_HEIGHT, enemy.position.z),
+                                &hittables,
+                            ),
+                            EnemyMarkers::AfterFiring => {
+                                game_params.enemy_shots.get(&enemy.id).is_some_and(
+                                    |(_, fired_at)| now - fired_at < ENEMY_MARKER_FIRE_DURATION,
+                                )
+                            }
+                        };
+                    if is_visible {
+                        draw_enemy_on_minimap(
+                            enemy,
+                            &game_params.mini_map,
+                            &game_params.mini_map_config,
+                            enemy.team.map_or(RED, |team| team.color()),
+                        );
+                    }
+                }
+            }
+        }
+        Err(e) => println!("Error while locking hittables {:?}", e),
+    }
+}
 /*
-fn draw_enemies_on_minimap(enemies: &Vec<Player>, game_params: &GameParams) {
+    remember when each enemy has fired last time, based on changes of their shot counter, and show its tracers.
+    Returns the positions of the enemies that fired since the last call
+*/
+fn track_enemy_shots(
+    enemies: &[Player],
+    enemy_shots: &mut HashMap<String, (u32, f64)>,
+    shots: &mut Vec<Shot>,
+) -> Vec<Vec3> {
+    let now = get_time();
+    let mut fired = vec![];
     for enemy in enemies {
-        if let PlayerStatus::Active = enemy.player_status {
-            draw_enemy_on_minimap(
-                &enemy,
-                &game_params.mini_map,
-                &game_params.mini_map_config,
-                RED,
+        match enemy_shots.get_mut(&enemy.id) {
+            Some((shots_fired, fired_at)) => {
+                if *shots_fired != enemy.shots_fired {
+                    *shots_fired = enemy.shots_fired;
+                    *fired_at = now;
+                    fired.push(vec3(enemy.position.x, PLAYER_HEIGHT, enemy.position.z));
+                    for tracer in &enemy.tracers {
+                        shots.push(Shot {
+                            start: Vec3::from_array(tracer.start),
+                            end: Vec3::from_array(tracer.end),
+                            time_out: SHOT_DURATION,
+                            color: tracer.weapon.spec().color,
+                        });
+                    }
+                }
+            }
+            None => {
+                enemy_shots.insert(enemy.id.clone(), (enemy.shots_fired, f64::NEG_INFINITY));
+            }
+        }
+    }
+    fired
+}
+//remember when each enemy took damage last time, based on changes of their damage counter
+fn track_enemy_hits(enemies: &[Player], enemy_hits: &mut HashMap<String, (u32, f64)>) {
+    let now = get_time();
+    for enemy in enemies {
+        match enemy_hits.get_mut(&enemy.id) {
+            Some((damage_taken, hit_at)) => {
+                if *damage_taken != enemy.damage_taken {
+                    *damage_taken = enemy.damage_taken;
+                    *hit_at = now;
+                }
+            }
+            None => {
+                enemy_hits.insert(enemy.id.clone(), (enemy.damage_taken, f64::NEG_INFINITY));
+            }
+        }
+    }
+}
+//eyeball looking where the player looks, tinted by the team and flashing red while it takes damage
+fn draw_avatar(enemy: &Player, hit_flash: bool) {
+    let center = vec3(enemy.position.x, PLAYER_HEIGHT, enemy.position.z);
+    let facing = enemy.facing();
+    let eyeball = match (hit_flash, enemy.team) {
+        (true, _) => Color::new(1.0, 0.3, 0.3, 1.0),
+        (false, Some(team)) => {
+            let color = team.color();
+            Color::new(
+                (1.0 + color.r) / 2.0,
+                (1.0 + color.g) / 2.0,
+                (1.0 + color.b) / 2.0,
+                1.0,
+            )
+        }
+        (false, None) => WHITE,
+    };
+    draw_sphere(center, AVATAR_RADIUS, None, eyeball);
+    draw_sphere(
+        center + facing * AVATAR_RADIUS * 0.75,
+        AVATAR_RADIUS * 0.4,
+        None,
+        Color::new(0.2, 0.45, 0.3, 1.0),
+    );
+    draw_sphere(
+        center + facing * AVATAR_RADIUS * 0.95,
+        AVATAR_RADIUS * 0.2,
+        None,
+        BLACK,
+    );
+}
+/*
+    name, score and distance above the avatar of every enemy in front of the camera.
+    Labels of enemies behind walls are hidden so they do not give away positions
+*/
+fn draw_nameplates(
+    enemies: &[Player],
+    camera: &Camera3D,
+    hittables: &[Hittable],
+    font: &Font,
+    layout: &Layout,
+) {
+    for enemy in enemies {
+        let center = vec3(enemy.position.x, PLAYER_HEIGHT, enemy.position.z);
+        if !is_in_line_of_sight(camera.position, center, hittables) {
+            continue;
+        }
+        let above = center + vec3(0.0, AVATAR_RADIUS + NAMEPLATE_OFFSET, 0.0);
+        let Some(point) = project_to_viewport(camera, above, layout.viewport) else {
+            continue;
+        };
+        let details = format!("{}  {:.1}m", enemy.score, camera.position.distance(center));
+        let lines = [
+            (
+                enemy.name.as_str(),
+                layout.scale,
+                enemy.team.map_or(WHITE, |team| team.color()),
+            ),
+            (details.as_str(), layout.scale * 0.75, LIGHTGRAY),
+        ];
+        //the details line sits right above the avatar, the name above it
+        let mut y = point.y;
+        for (text, font_scale, color) in lines.into_iter().rev() {
+            let size = measure_text(text, Some(font), GAME_FONT_SIZE, font_scale);
+            draw_text_ex(
+                text,
+                point.x - size.width / 2.0,
+                y,
+                TextParams {
+                    font: Some(font),
+                    font_size: GAME_FONT_SIZE,
+                    font_scale,
+                    font_scale_aspect: 1.0,
+                    rotation: 0.0,
+                    color,
+                },
             );
+            y -= size.height + 2.0 * layout.scale;
         }
     }
 }
+//screen position of a point of the 3d view, None when it is behind the camera or outside the view
+fn project_to_viewport(camera: &Camera3D, point: Vec3, viewport: Rect) -> Option<Vec2> {
+    let clip = camera.matrix() * point.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 || ndc.z.abs() > 1.0 {
+        return None;
+    }
+    Some(vec2(
+        viewport.x + (ndc.x + 1.0) / 2.0 * viewport.w,
+        viewport.y + (1.0 - ndc.y) / 2.0 * viewport.h,
+    ))
+}
+//where the last shot of the player left its eye
+fn muzzle_position(player: &Player) -> Option<Vec3> {
+    player
+        .tracers
+        .first()
+        .map(|tracer| tracer.start)
+        .map(Vec3::from_array)
+        .or_else(|| {
+            player.projectiles.last().map(|launch| {
+                Vec3::from_array(launch.origin) + Vec3::from_array(launch.direction) * MUZZLE_OFFSET
+            })
+        })
+}
+/*
+    damage for an enemy hit by a ray or a projectile, walls and protected players stop the shot without damage.
+    Returns true when the enemy took damage
+*/
+fn hit_enemy(
+    hittable: Hittable,
+    damage: u32,
+    player: &mut Player,
+    friendly_fire: bool,
+    hittables: &mut Vec<Hittable>,
+    socket: &Arc<UdpSocket>,
+    server_addr: SocketAddr,
+) -> bool {
+    let mut enemy = match hittable {
+        Hittable::Wall(_) => return false,
+        Hittable::Enemy(enemy) => enemy,
+    };
+    if !enemy.take_hit(player, damage, friendly_fire) {
+        return false;
+    }
+    let killed = matches!(enemy.player_status, PlayerStatus::Killed);
+
+    //remove killed enemies from hitables, keep the damage of the others
+    //until the next update from the server
+    let _hittables: Vec<Hittable> = hittables
+        .iter()
+        .filter_map(|hittable| match hittable {
+            Hittable::Enemy(_enemy) if _enemy.id == enemy.id => {
+                (!killed).then(|| Hittable::Enemy(enemy.clone()))
+            }
+            _ => Some(hittable.clone()),
+        })
+        .collect();
+    *hittables = _hittables;
+
+    //notify server
+    send_message_to_server(socket, server_addr, &enemy, &player.id);
+    true
+}
+/*
+    moves every projectile by one frame, it is gone once it hits something or its lifetime is over.
+    Only projectiles of this player do damage, returns the ids of those that are gone and whether one hit an enemy
 */
-fn draw_shots(shots: &Vec<Shot>) {
+#[allow(clippy::too_many_arguments)]
+fn update_projectiles(
+    projectiles: &mut Vec<Projectile>,
+    delta: f32,
+    player: &mut Player,
+    friendly_fire: bool,
+    hittables: &mut Vec<Hittable>,
+    effects: &mut Effects,
+    socket: &Arc<UdpSocket>,
+    server_addr: SocketAddr,
+) -> (Vec<u32>, bool) {
+    let mut ended = vec![];
+    let mut enemy_hit = false;
+    projectiles.retain_mut(|projectile| {
+        let step = projectile.velocity * delta;
+        projectile.lifetime -= delta;
+        //only hits within this frame's step count
+        //a projectile starts inside the eyeball of its owner
+        let hit = closest_hit(
+            hittables,
+            projectile.position,
+            step,
+            projectile.owner.as_deref(),
+        )
+        .filter(|hit| hit.t <= 1.0);
+        let alive = hit.is_none() && projectile.lifetime > 0.0;
+        if let Some(Hit {
+            p,
+            hittable: Hittable::Wall(shield),
+            ..
+        }) = &hit
+        {
+            effects.wall_impact(*p, shield, step, projectile.color);
+        }
+        if projectile.owner.is_none() {
+            if let Some(hit) = hit {
+                enemy_hit |= hit_enemy(
+                    hit.hittable,
+                    projectile.damage,
+                    player,
+                    friendly_fire,
+                    hittables,
+                    socket,
+                    server_addr,
+                );
+            }
+            if !alive {
+                ended.push(projectile.id);
+            }
+        }
+        projectile.position += step;
+        alive
+    });
+    (ended, enemy_hit)
+}
+//projectiles of other players start when their launch shows up in the roster and end once the owner drops it
+fn sync_remote_projectiles(
+    projectiles: &mut Vec<Projectile>,
+    seen_launches: &mut HashSet<(String, u32)>,
+    roster: &[Player],
+    player_id: &str,
+) {
+    let mut launches = HashSet::new();
+    for _player in roster.iter().filter(|_player| _player.id != player_id) {
+        for launch in &_player.projectiles {
+            let key = (_player.id.clone(), launch.id);
+            if !seen_launches.contains(&key) {
+                //the owner applies the damage
+                if let Some(projectile) = Projectile::launch(Some(_player.id.clone()), launch, 0) {
+                    projectiles.push(projectile);
+                }
+            }
+            launches.insert(key);
+        }
+    }
+    projectiles.retain(|projectile| match projectile.owner {
+        Some(ref owner) => launches.contains(&(owner.clone(), projectile.id)),
+        None => true,
+    });
+    *seen_launches = launches;
+}
+//glowing ball with a short trail behind it
+fn draw_projectiles(projectiles: &[Projectile]) {
+    for projectile in projectiles {
+        draw_sphere(projectile.position, 0.03, None, projectile.color);
+        draw_line_3d(
+            projectile.position - projectile.velocity * 0.05,
+            projectile.position,
+            projectile.color,
+        );
+    }
+}
+fn draw_shots(shots: &[Shot]) {
     for shot in shots {
         draw_line_3d(shot.start, shot.end, shot.color);
     }
 }
 fn remove_shots(shots: &mut Vec<Shot>) {
-    for i in 0..shots.len() {
-        shots[i].time_out -= 1;
+    for shot in shots.iter_mut() {
+        shot.time_out -= 1;
     }
     let filtered: Vec<Shot> = shots
         .iter()
@@ -1538,7 +2273,7 @@ fn remove_shots(shots: &mut Vec<Shot>) {
         .collect();
     *shots = filtered;
 }
-fn add_shields(hittables_ref: Arc<Mutex<Vec<Hittable>>>, mini_map: &Vec<Vec<bool>>) {
+fn add_shields(hittables_ref: Arc<Mutex<Vec<Hittable>>>, mini_map: &[Vec<bool>]) {
     match hittables_ref.lock() {
         Ok(mut hittables) => {
             for (z, row) in mini_map.iter().enumerate() {
@@ -1589,16 +2324,17 @@ fn add_shields(hittables_ref: Arc<Mutex<Vec<Hittable>>>, mini_map: &Vec<Vec<bool
 }
 fn send_message_to_server(
     socket: &Arc<UdpSocket>,
-    server_addr: &String,
+    server_addr: SocketAddr,
     player: &Player,
-    sender_id: &String,
+    sender_id: &str,
 ) {
-
-    let server_object = ServerMessage { sender_id: sender_id.clone(), player: player.clone() };
-    let server_addr = server_addr.clone();
+    let server_object = ServerMessage {
+        sender_id: sender_id.to_string(),
+        player: player.clone(),
+    };
     let socket = Arc::clone(socket);
 
-    thread::spawn(move || {  
+    thread::spawn(move || {
         if let Ok(message_to_server) = serde_json::to_string(&server_object) {
             // if let Err(e) = socket.send_to(message_to_server.as_bytes(), server_addr) {
             //     println!(
@@ -1609,6 +2345,24 @@ fn send_message_to_server(
             let _ = socket.send_to(message_to_server.as_bytes(), server_addr);
         }
     });
-
-
+}
+fn send_chat_to_server(socket: &Arc<UdpSocket>, server_addr: SocketAddr, request: &ChatRequest) {
+    match serde_json::to_string(request) {
+        Ok(message_to_server) => {
+            if let Err(e) = socket.send_to(message_to_server.as_bytes(), server_addr) {
+                println!("Error while sending chat message to server: {:?}", e);
+            }
+        }
+        Err(e) => println!("Error while serializing chat message: {:?}", e),
+    }
+}
+fn send_status_query(socket: &Arc<UdpSocket>, server_addr: SocketAddr) {
+    match serde_json::to_string(&StatusQuery::new()) {
+        Ok(query) => {
+            if let Err(e) = socket.send_to(query.as_bytes(), server_addr) {
+                println!("Error while sending status query to server: {:?}", e);
+            }
+        }
+        Err(e) => println!("Error while serializing status query: {:?}", e),
+    }
 }
@@ -1,8 +1,16 @@
+use crate::bot::Bot;
+use crate::chat::Chat;
+use crate::effects::Effects;
+use crate::feed::KillFeed;
+use crate::input::RebindMenu;
+use crate::layout::Layout;
 use crate::preferences::*;
+use crate::weapons::{WeaponKind, Weapons};
 use macroquad::prelude::*;
-use macroquad::prelude::{Image, Texture2D, Vec2, Vec3};
+use macroquad::prelude::{Texture2D, Vec2, Vec3};
 use macroquad::{color::Color, texture::RenderTarget};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
@@ -24,11 +32,68 @@ pub struct Player {
     pub player_status: PlayerStatus,
     pub orientation: f32,
     pub current_map: String,
+    #[serde(default)]
+    pub shots_fired: u32,
+    //rays of the shot of a weapon that hits instantly, only sent with the update of that shot. The other clients draw them as tracers
+    #[serde(default)]
+    pub tracers: Vec<Tracer>,
+    //projectiles of this player still in flight, the other clients draw them
+    #[serde(default)]
+    pub projectiles: Vec<ProjectileLaunch>,
+    //spectators are neither drawn nor hittable
+    #[serde(default)]
+    pub spectator: bool,
+    //players without a team play free for all
+    #[serde(default)]
+    pub team: Option<Team>,
+    //enemy flag this player carries in capture the flag
+    #[serde(default)]
+    pub carrying_flag: Option<Team>,
+    //flag this player dropped on death, until somebody picks it up or it returns to its base
+    #[serde(default)]
+    pub dropped_flag: Option<DroppedFlag>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub flag_dropped_at: f64,
+    #[serde(default)]
+    pub captures: u32,
+    #[serde(default)]
+    pub kills: u32,
+    #[serde(default)]
+    pub deaths: u32,
+    //round trip to the server in milliseconds, measured by the player's own client
+    #[serde(default)]
+    pub ping: u32,
+    //seconds since the epoch when the last status query was sent
+    #[serde(skip_serializing, skip_deserializing)]
+    pub ping_query_time: Option<f64>,
+    #[serde(default = "default_health")]
+    pub health: u32,
+    //total damage dealt to this player, only ever raised by the shooters so that
+    //the player can tell new hits from its own updates echoed by the server
+    #[serde(default)]
+    pub damage_taken: u32,
+    //seconds since the epoch when the next health point is regenerated
+    #[serde(skip_serializing, skip_deserializing)]
+    pub health_regen_at: f64,
+    //id of the player who hit this one last, it made the kill once health is gone
+    #[serde(default)]
+    pub killed_by: Option<String>,
+    //shots do not kill a player who just respawned
+    #[serde(default)]
+    pub spawn_protected: bool,
+    //seconds since the epoch, set while the death screen is shown
+    #[serde(skip_serializing, skip_deserializing)]
+    pub death_time: Option<f64>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub killer_name: Option<String>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub spawn_protected_until: f64,
     #[serde(skip_serializing, skip_deserializing)]
     pub mini_map: Vec<Vec<bool>>,
     #[serde(skip_serializing, skip_deserializing)]
     pub yaw: f32,
-    #[serde(skip_serializing, skip_deserializing)]
+    //replicated so that the avatar looks up and down with the player
+    #[serde(default)]
     pub pitch: f32,
     #[serde(skip_serializing, skip_deserializing)]
     pub front: Vec3,
@@ -47,6 +112,27 @@ impl Player {
             player_status: PlayerStatus::Active,
             orientation: 0.0,
             current_map: String::from(""),
+            shots_fired: 0,
+            tracers: vec![],
+            projectiles: vec![],
+            spectator: false,
+            team: None,
+            carrying_flag: None,
+            dropped_flag: None,
+            flag_dropped_at: 0.0,
+            captures: 0,
+            kills: 0,
+            deaths: 0,
+            ping: 0,
+            ping_query_time: None,
+            health: MAX_HEALTH,
+            damage_taken: 0,
+            health_regen_at: 0.0,
+            killed_by: None,
+            spawn_protected: false,
+            death_time: None,
+            killer_name: None,
+            spawn_protected_until: 0.0,
             mini_map: vec![],
             yaw: 0.0,
             pitch: 0.0,
@@ -55,9 +141,61 @@ impl Player {
             position_vec3: vec3(1.0, 1.0, 1.0),
         }
     }
+    //unit vector the player looks along, from the replicated orientation and pitch
+    pub fn facing(&self) -> Vec3 {
+        vec3(
+            self.orientation.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            -self.orientation.cos() * self.pitch.cos(),
+        )
+    }
+    pub fn is_teammate(&self, other: &Player) -> bool {
+        self.team.is_some() && self.team == other.team
+    }
+    /*
+        damage of a shot by attacker on this player as an enemy, protected players and teammates without
+        friendly fire take none. The shot that takes the last health makes the kill. Returns true when damage was done
+    */
+    pub fn take_hit(&mut self, attacker: &mut Player, damage: u32, friendly_fire: bool) -> bool {
+        if self.spawn_protected || (attacker.is_teammate(self) && !friendly_fire) {
+            return false;
+        }
+        self.health = self.health.saturating_sub(damage);
+        self.damage_taken += damage;
+        self.killed_by = Some(attacker.id.clone());
+        if self.health == 0 {
+            //update score
+            if let PlayerStatus::Active = self.player_status {
+                attacker.score += 1;
+                attacker.kills += 1;
+            }
+            self.player_status = PlayerStatus::Killed;
+        }
+        true
+    }
+    /*
+        hits by other players arrive as records of this player with a higher damage counter or a kill.
+        Updates sent before the server got the respawn are ignored. Returns true when the health changed
+    */
+    pub fn apply_remote_damage(&mut self, record: &Player, now: f64) -> bool {
+        let damage = record.damage_taken.saturating_sub(self.damage_taken);
+        let killed = matches!(record.player_status, PlayerStatus::Killed);
+        self.damage_taken = u32::max(self.damage_taken, record.damage_taken);
+        if (damage == 0 && !killed) || self.death_time.is_some() || self.spawn_protected {
+            return false;
+        }
+        self.health = if killed {
+            0
+        } else {
+            self.health.saturating_sub(damage)
+        };
+        self.health_regen_at = now + HEALTH_REGEN_DELAY;
+        self.killed_by = record.killed_by.clone();
+        true
+    }
     //function that when player is used as enemy calculate if it was hit by other player
     pub fn hit(&self, origin: Vec3, direction: Vec3) -> Option<Hit> {
-        let hittable = Hittable::Enemy(self.clone());
+        let hittable = Hittable::Enemy(Box::new(self.clone()));
         //calculate components
 
         let a = direction.dot(direction);
@@ -67,7 +205,7 @@ impl Player {
         let position = vec3(self.position.x, PLAYER_HEIGHT, self.position.z);
         let c_o = position - origin;
         let b = -2.0 * (c_o.dot(direction));
-        let c = c_o.dot(c_o) - ENEMY_RADIUS * ENEMY_RADIUS;
+        let c = c_o.dot(c_o) - AVATAR_RADIUS * AVATAR_RADIUS;
         let discriminant = b * b - 4.0 * a * c;
         if discriminant < 0.0 {
             return None;
@@ -101,6 +239,33 @@ impl Player {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Team {
+    Red,
+    Blue,
+}
+impl Team {
+    pub const ALL: [Team; 2] = [Team::Red, Team::Blue];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+    pub fn color(&self) -> Color {
+        match self {
+            Team::Red => Color::new(1.0, 0.3, 0.3, 1.0),
+            Team::Blue => Color::new(0.3, 0.5, 1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DroppedFlag {
+    pub team: Team,
+    pub position: Position,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Position {
     pub x: f32,
@@ -118,14 +283,17 @@ impl Position {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Status {
     EnterIP,
     EnterName,
     StartServerListener,
+    SelectTeam,
     SelectMap,
     Init,
     Run,
+    Options,
+    BrowseServers,
 }
 
 #[derive(Debug, Clone)]
@@ -138,7 +306,7 @@ pub struct MiniMapConfig {
 }
 impl MiniMapConfig {
     pub fn new(
-        mini_map: &Vec<Vec<bool>>,
+        mini_map: &[Vec<bool>],
         mini_map_width: f32,
         mini_map_height: f32,
         horizontal_offset: f32,
@@ -163,9 +331,9 @@ impl MiniMapConfig {
 pub struct GameParams {
     pub wall_texture: Texture2D,
     pub arrow_texture: Texture2D,
-    pub eye_texture: Image,
     pub floor_texture: Texture2D,
     pub mini_map_config: MiniMapConfig,
+    pub layout: Layout,
     pub render_target: RenderTarget,
     pub last_mouse_position: Vec2,
     pub mini_map: Vec<Vec<bool>>,
@@ -175,6 +343,26 @@ pub struct GameParams {
     pub mini_map_height_as_usize: usize,
     pub world_up: Vec3,
     pub shots: Vec<Shot>,
+    pub projectiles: Vec<Projectile>,
+    //launches of other players that already have a projectile, by owner id and launch id
+    pub seen_launches: HashSet<(String, u32)>,
+    pub weapons: Weapons,
+    pub effects: Effects,
+    pub rebind_menu: Option<RebindMenu>,
+    pub bot: Option<Bot>,
+    //enemy id -> (last known shot counter, time when it changed)
+    pub enemy_shots: HashMap<String, (u32, f64)>,
+    //enemy id -> (last known damage counter, time when it changed)
+    pub enemy_hits: HashMap<String, (u32, f64)>,
+    pub chat: Chat,
+    pub feed: Arc<Mutex<KillFeed>>,
+    //every player in the last update from the server, including this one
+    pub roster: Arc<Mutex<Vec<Player>>>,
+    pub last_ping_query: f64,
+    //round of the match this client is playing
+    pub round: u32,
+    //cell centers of the flag bases, empty when the map has none
+    pub flag_bases: Vec<(Team, Vec2)>,
     pub hittables: Arc<Mutex<Vec<Hittable>>>,
 }
 
@@ -186,6 +374,54 @@ pub struct Shot {
     pub color: Color,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tracer {
+    pub weapon: WeaponKind,
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+}
+
+//where and how a projectile was fired, replicated with the player record
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectileLaunch {
+    //unique among the projectiles of the same player
+    pub id: u32,
+    pub weapon: WeaponKind,
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+//shot of a projectile weapon, moved every frame until it hits something or its lifetime is over
+#[derive(Debug, Clone)]
+pub struct Projectile {
+    //None for projectiles of this player, only those do damage
+    pub owner: Option<String>,
+    pub id: u32,
+    pub position: Vec3,
+    //units per second
+    pub velocity: Vec3,
+    //seconds left
+    pub lifetime: f32,
+    pub damage: u32,
+    pub color: Color,
+}
+impl Projectile {
+    //None when the weapon of the launch has no projectiles
+    pub fn launch(owner: Option<String>, launch: &ProjectileLaunch, damage: u32) -> Option<Self> {
+        let spec = launch.weapon.spec();
+        let projectile = spec.projectile?;
+        Some(Projectile {
+            owner,
+            id: launch.id,
+            position: Vec3::from_array(launch.origin),
+            velocity: Vec3::from_array(launch.direction).normalize_or_zero() * projectile.speed,
+            lifetime: projectile.lifetime,
+            damage,
+            color: spec.color,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Shield {
     pub q: Vec3, //origin
@@ -205,7 +441,7 @@ impl Shield {
             return None;
         }
         let t = (n.dot(self.q) - n.dot(origin)) / denominator;
-        if t < MIN_SHOT_HIT_TIME || t > MAX_SHOT_HIT_TIME {
+        if !(MIN_SHOT_HIT_TIME..=MAX_SHOT_HIT_TIME).contains(&t) {
             return None;
         }
         let p = origin + direction * t;
@@ -225,6 +461,35 @@ impl Shield {
     }
 }
 
+//closest wall or enemy along the ray, t of the hit is in units of direction. The ignored player can not be hit
+pub fn closest_hit(
+    hittables: &[Hittable],
+    origin: Vec3,
+    direction: Vec3,
+    ignored: Option<&str>,
+) -> Option<Hit> {
+    hittables
+        .iter()
+        .filter_map(|hittable| match hittable {
+            Hittable::Wall(shield) => shield.hit(origin, direction),
+            Hittable::Enemy(enemy) if ignored == Some(enemy.id.as_str()) => None,
+            Hittable::Enemy(enemy) => enemy.hit(origin, direction),
+        })
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+}
+
+//true when no wall stands between origin and target
+pub fn is_in_line_of_sight(origin: Vec3, target: Vec3, hittables: &[Hittable]) -> bool {
+    let direction = target - origin;
+    !hittables.iter().any(|hittable| {
+        if let Hittable::Wall(shield) = hittable {
+            shield.hit(origin, direction).is_some_and(|hit| hit.t < 1.0)
+        } else {
+            false
+        }
+    })
+}
+
 #[derive(Debug)]
 pub struct Hit {
     pub t: f32,
@@ -235,7 +500,7 @@ pub struct Hit {
 #[derive(Debug, Clone)]
 pub enum Hittable {
     Wall(Shield),
-    Enemy(Player),
+    Enemy(Box<Player>),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -243,3 +508,266 @@ pub struct ServerMessage {
     pub sender_id: String,
     pub player: Player,
 }
+
+//which enemies are shown on the mini map, decided by the server for the whole match
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum EnemyMarkers {
+    #[default]
+    Always,
+    LineOfSight,
+    AfterFiring,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum GameMode {
+    //no end, scores keep climbing
+    #[default]
+    Deathmatch,
+    //like timed deathmatch, the limits apply to the team totals
+    TeamDeathmatch,
+    //the round ends after time_limit seconds or once a player reaches frag_limit
+    TimedDeathmatch,
+    //killed players stay dead until only one is left
+    LastManStanding,
+    //flags are taken from the enemy base to the own one, needs a map with flag bases
+    CaptureTheFlag,
+}
+
+fn default_health() -> u32 {
+    MAX_HEALTH
+}
+fn default_shot_damage() -> u32 {
+    SHOT_DAMAGE
+}
+fn default_health_regen() -> u32 {
+    HEALTH_REGEN
+}
+fn default_respawn_delay() -> f32 {
+    RESPAWN_DELAY
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchOptions {
+    #[serde(default)]
+    pub enemy_markers: EnemyMarkers,
+    #[serde(default)]
+    pub mode: GameMode,
+    //seconds, 0 is no limit
+    #[serde(default)]
+    pub time_limit: u32,
+    //0 is no limit
+    #[serde(default)]
+    pub frag_limit: u32,
+    //whether shots kill players of the own team
+    #[serde(default)]
+    pub friendly_fire: bool,
+    #[serde(default = "default_health")]
+    pub max_health: u32,
+    #[serde(default = "default_shot_damage")]
+    pub shot_damage: u32,
+    //health points per second, 0 turns regeneration off
+    #[serde(default = "default_health_regen")]
+    pub health_regen: u32,
+    //seconds between death and respawn, the same for everybody in the match
+    #[serde(default = "default_respawn_delay")]
+    pub respawn_delay: f32,
+}
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            enemy_markers: EnemyMarkers::default(),
+            mode: GameMode::default(),
+            time_limit: 0,
+            frag_limit: 0,
+            friendly_fire: false,
+            max_health: MAX_HEALTH,
+            shot_damage: SHOT_DAMAGE,
+            health_regen: HEALTH_REGEN,
+            respawn_delay: RESPAWN_DELAY,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum MatchPhase {
+    Warmup,
+    #[default]
+    InProgress,
+    //between rounds, the winner screen is shown
+    Intermission,
+}
+
+//announced by the server with every update
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MatchState {
+    #[serde(default)]
+    pub phase: MatchPhase,
+    //seconds left in the phase when the update was sent, 0 when the phase has no end
+    #[serde(default)]
+    pub remaining: f32,
+    //id of the winner of the last round
+    #[serde(default)]
+    pub winner: Option<String>,
+    //increases with every restart, scores are reset when it changes
+    #[serde(default)]
+    pub round: u32,
+}
+
+//options and state of the match, shared between the listener and the game loop
+#[derive(Debug, Clone, Default)]
+pub struct MatchStatus {
+    pub options: MatchOptions,
+    pub state: MatchState,
+    //seconds since the epoch when the state was received
+    pub received_at: f64,
+}
+impl MatchStatus {
+    pub fn remaining(&self, now: f64) -> f32 {
+        f32::max(0.0, self.state.remaining - (now - self.received_at) as f32)
+    }
+    //players only respawn during last man standing rounds once the round is over
+    pub fn allows_respawn(&self) -> bool {
+        self.options.mode != GameMode::LastManStanding || self.state.phase != MatchPhase::InProgress
+    }
+}
+
+//server sends either the plain list of players or the list together with match options
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ServerUpdate {
+    Chat {
+        chat: ChatMessage,
+    },
+    Match {
+        players: Vec<Player>,
+        #[serde(default)]
+        options: MatchOptions,
+        #[serde(default)]
+        state: MatchState,
+    },
+    Players(Vec<Player>),
+    //answer to the status query used to measure the ping, only its arrival matters
+    #[allow(dead_code)]
+    Status(ServerInfo),
+}
+
+//chat line, the client sends it as {"chat": ...} and the server relays it to every player
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatMessage {
+    pub sender_id: String,
+    pub name: String,
+    pub text: String,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatRequest {
+    pub chat: ChatMessage,
+}
+
+//sent to a known server, or broadcast on the local network, to ask for its status
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatusQuery {
+    pub status: bool,
+    pub protocol: u32,
+}
+impl StatusQuery {
+    pub fn new() -> Self {
+        StatusQuery {
+            status: true,
+            protocol: PROTOCOL_VERSION,
+        }
+    }
+}
+
+//answer of a server to a status query
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerInfo {
+    pub name: String,
+    #[serde(default)]
+    pub map: String,
+    #[serde(default)]
+    pub players: usize,
+    #[serde(default)]
+    pub protocol: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: &str, team: Option<Team>) -> Player {
+        let mut player = Player::new();
+        player.id = String::from(id);
+        player.team = team;
+        player
+    }
+
+    #[test]
+    fn only_the_hit_taking_the_last_health_kills() {
+        let mut attacker = player("a", None);
+        let mut enemy = player("b", None);
+        assert!(enemy.take_hit(&mut attacker, MAX_HEALTH - 1, false));
+        assert_eq!(enemy.health, 1);
+        assert!(matches!(enemy.player_status, PlayerStatus::Active));
+        assert_eq!(attacker.kills, 0);
+
+        assert!(enemy.take_hit(&mut attacker, SHOT_DAMAGE, false));
+        assert_eq!(enemy.health, 0);
+        assert_eq!(enemy.damage_taken, MAX_HEALTH - 1 + SHOT_DAMAGE);
+        assert_eq!(enemy.killed_by.as_deref(), Some("a"));
+        assert!(matches!(enemy.player_status, PlayerStatus::Killed));
+        assert_eq!((attacker.kills, attacker.score), (1, 1));
+    }
+
+    #[test]
+    fn protected_players_and_teammates_take_no_damage() {
+        let mut attacker = player("a", Some(Team::Red));
+        let mut protected = player("b", Some(Team::Blue));
+        protected.spawn_protected = true;
+        assert!(!protected.take_hit(&mut attacker, SHOT_DAMAGE, false));
+        assert_eq!(protected.health, MAX_HEALTH);
+
+        let mut teammate = player("c", Some(Team::Red));
+        assert!(!teammate.take_hit(&mut attacker, SHOT_DAMAGE, false));
+        assert_eq!(teammate.damage_taken, 0);
+        assert!(teammate.take_hit(&mut attacker, SHOT_DAMAGE, true));
+        assert_eq!(teammate.health, MAX_HEALTH - SHOT_DAMAGE);
+    }
+
+    #[test]
+    fn remote_damage_is_the_increase_of_the_damage_counter() {
+        let mut local = player("a", None);
+        let mut record = local.clone();
+        record.damage_taken = SHOT_DAMAGE;
+        record.killed_by = Some(String::from("b"));
+        assert!(local.apply_remote_damage(&record, 10.0));
+        assert_eq!(local.health, MAX_HEALTH - SHOT_DAMAGE);
+        assert_eq!(local.health_regen_at, 10.0 + HEALTH_REGEN_DELAY);
+        assert_eq!(local.killed_by.as_deref(), Some("b"));
+
+        //the same record echoed again does no damage
+        assert!(!local.apply_remote_damage(&record, 11.0));
+        assert_eq!(local.health, MAX_HEALTH - SHOT_DAMAGE);
+
+        record.player_status = PlayerStatus::Killed;
+        assert!(local.apply_remote_damage(&record, 12.0));
+        assert_eq!(local.health, 0);
+    }
+
+    #[test]
+    fn remote_damage_is_ignored_while_dead_or_protected() {
+        let mut local = player("a", None);
+        let mut record = local.clone();
+        record.damage_taken = SHOT_DAMAGE;
+        local.spawn_protected = true;
+        assert!(!local.apply_remote_damage(&record, 0.0));
+        assert_eq!(local.health, MAX_HEALTH);
+        //the counter is still taken over, so the hit is not applied later
+        assert_eq!(local.damage_taken, SHOT_DAMAGE);
+
+        local.spawn_protected = false;
+        local.death_time = Some(0.0);
+        record.damage_taken = 2 * SHOT_DAMAGE;
+        assert!(!local.apply_remote_damage(&record, 0.0));
+        assert_eq!(local.health, MAX_HEALTH);
+    }
+}
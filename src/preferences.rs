@@ -13,24 +13,130 @@ pub const MAP_MARGIN_TOP: f32 = 450.0;
 pub const NAME_MARGIN_TOP: f32 = 585.0;
 pub const NAME_MARGIN_LEFT: f32 = 190.0;
 pub const SCORE_MARGIN_LEFT: f32 = 360.0;
+pub const SCOREBOARD_WIDTH: f32 = 226.0;
+pub const SCOREBOARD_LINE_HEIGHT: f32 = 25.0;
 
 pub const FPS_MARGIN_LEFT: f32 = 4.0;
 pub const FPS_MARGIN_TOP: f32 = 14.0;
 pub const CONSOLE_FONT_SIZE: f32 = 18.0;
 pub const GAME_FONT_SIZE: u16 = 10;
 
-pub const MOVE_SPEED: f32 = 0.03;
-pub const LOOK_SPEED: f32 = 0.8;
+//same as the values the settings file used to ship with
+pub const MOVE_SPEED: f32 = 0.04;
+pub const LOOK_SPEED: f32 = 0.25;
+pub const MAX_MOVE_SPEED: f32 = 0.2;
+pub const MAX_LOOK_SPEED: f32 = 5.0;
+pub const DEFAULT_FOV: f32 = 45.0;
+pub const MIN_FOV: f32 = 30.0;
+pub const MAX_FOV: f32 = 120.0;
 pub const SHOT_DURATION: i32 = 10;
 pub const MAX_SHOT_RANGE: f32 = 500.0;
+//seconds before a weapon fires after switching to it
+pub const WEAPON_SWITCH_TIME: f64 = 0.4;
 pub const MAX_SHOT_HIT_TIME: f32 = 100000.0;
 pub const MIN_SHOT_HIT_TIME: f32 = 0.000001;
 
-pub const ENEMY_RADIUS: f32 = 0.05;
+//chat overlay at the bottom of the 3d view
+pub const CHAT_MARGIN_LEFT: f32 = 120.0;
+pub const CHAT_MARGIN_TOP: f32 = 340.0;
+pub const CHAT_WIDTH: f32 = 366.0;
+pub const CHAT_HEIGHT: f32 = 85.0;
+pub const CHAT_VISIBLE_LINES: usize = 5;
+pub const CHAT_LOG_LENGTH: usize = 50;
+pub const MAX_CHAT_LENGTH: usize = 80;
+//seconds a chat line stays visible while not typing
+pub const CHAT_LINE_DURATION: f64 = 10.0;
+//at most CHAT_RATE_LIMIT messages every CHAT_RATE_WINDOW seconds
+pub const CHAT_RATE_LIMIT: usize = 3;
+pub const CHAT_RATE_WINDOW: f64 = 5.0;
+
+//kill feed in the top right corner of the 3d view
+pub const FEED_MARGIN_LEFT: f32 = 200.0;
+pub const FEED_MARGIN_TOP: f32 = 25.0;
+pub const FEED_WIDTH: f32 = 286.0;
+pub const FEED_HEIGHT: f32 = 60.0;
+pub const FEED_VISIBLE_EVENTS: usize = 5;
+//seconds an event stays in the kill feed
+pub const FEED_EVENT_DURATION: f64 = 5.0;
+
+//seconds
+pub const RESPAWN_DELAY: f32 = 3.0;
+pub const SPAWN_PROTECTION_DURATION: f64 = 2.0;
+pub const DEATH_FADE_DURATION: f64 = 0.5;
+
+//health and damage, the server can change them per match
+pub const MAX_HEALTH: u32 = 100;
+pub const SHOT_DAMAGE: u32 = 34;
+//health points per second once HEALTH_REGEN_DELAY seconds passed without damage
+pub const HEALTH_REGEN: u32 = 5;
+pub const HEALTH_REGEN_DELAY: f64 = 5.0;
+pub const HEALTH_BAR_WIDTH: f32 = 120.0;
+pub const HEALTH_BAR_HEIGHT: f32 = 8.0;
+
+//seconds between status queries measuring the ping
+pub const PING_INTERVAL: f64 = 2.0;
+
+//map characters of the capture the flag bases
+pub const FLAG_BASE_RED: char = 'R';
+pub const FLAG_BASE_BLUE: char = 'B';
+pub const FLAG_PICKUP_RADIUS: f32 = 0.4;
+//seconds until a dropped flag goes back to its base
+pub const FLAG_RETURN_DELAY: f64 = 15.0;
+pub const FLAG_CAPTURE_SCORE: u32 = 5;
+
+//seconds an enemy stays on the mini map after firing
+pub const ENEMY_MARKER_FIRE_DURATION: f64 = 1.5;
+pub const MUZZLE_FLASH_DURATION: f64 = 0.08;
+
+//synthesized sound effects
+pub const SOUND_SAMPLE_RATE: u32 = 22050;
+//stereo positions every effect is loaded in
+pub const SOUND_PAN_STEPS: usize = 5;
+//how fast sounds get quieter with distance
+pub const SOUND_ROLLOFF: f32 = 0.3;
+pub const FOOTSTEP_DISTANCE: f32 = 0.5;
+//longer moves between two frames are respawns
+pub const FOOTSTEP_MAX_JUMP: f32 = 1.0;
+pub const MAX_VOLUME: f32 = 1.0;
+
+//impact marks of shots on walls, the oldest mark goes once there are too many
+pub const MAX_DECALS: usize = 64;
+pub const DECAL_SIZE: f32 = 0.04;
+//distance from the wall, keeps the mark from flickering with the wall texture
+pub const DECAL_WALL_OFFSET: f32 = 0.002;
+pub const DECAL_DURATION: f32 = 10.0;
+pub const IMPACT_PARTICLES: usize = 8;
+pub const IMPACT_PARTICLE_DURATION: f32 = 0.5;
+pub const IMPACT_PARTICLE_GRAVITY: f32 = 4.0;
+
+//size of the eyeball of a player, also the sphere shots hit
+pub const AVATAR_RADIUS: f32 = 0.15;
+//shot lines and the muzzle flash start this far in front of the eye
+pub const MUZZLE_OFFSET: f32 = AVATAR_RADIUS + 0.05;
+pub const HIT_FLASH_DURATION: f64 = 0.15;
+//distance of the name above the avatar
+pub const NAMEPLATE_OFFSET: f32 = 0.1;
 pub const PLAYER_HEIGHT: f32 = 1.0;
 
+//radians per second
+pub const BOT_TURN_SPEED: f32 = 3.0;
+pub const BOT_AIM_TOLERANCE: f32 = 0.03;
+pub const BOT_FIRE_INTERVAL: f32 = 0.8;
+pub const BOT_STUCK_DISTANCE: f32 = 0.001;
+
 pub const MAX_PITCH: f32 = 0.35;
 pub const MIN_PITCH: f32 = -0.35;
 
 pub const MAPS_DIRECTORY_PATH: &str = "maps";
-pub const DEFAULT_MAP_PATH: &str = "assets/map_one.txt";
+pub const DEFAULT_MAP_PATH: &str = "maps/map_one.txt";
+//written when the game is played, not part of the repository. settings.example.json shows the defaults
+pub const SETTINGS_PATH: &str = "settings.json";
+pub const MAX_RECENT_SERVERS: usize = 5;
+pub const DEFAULT_SERVER_PORT: u16 = 4000;
+//largest udp payload
+pub const MAX_PACKET_SIZE: usize = 65507;
+//servers answering status queries with another version are marked in the server browser
+pub const PROTOCOL_VERSION: u32 = 1;
+
+//seconds to wait for answers to a status query
+pub const DISCOVERY_TIMEOUT: f64 = 2.0;
@@ -0,0 +1,136 @@
+use crate::layout::Layout;
+use crate::models::*;
+use crate::preferences::*;
+use crate::scoreboard::{draw_scoreboard, team_totals, with_local_player};
+use macroquad::prelude::*;
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds.ceil() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn draw_centered(text: &str, y: f32, font_scale: f32, color: Color, font: &Font, layout: &Layout) {
+    let size = measure_text(text, Some(font), GAME_FONT_SIZE, font_scale);
+    draw_text_ex(
+        text,
+        layout.viewport.x + (layout.viewport.w - size.width) / 2.0,
+        y,
+        TextParams {
+            font: Some(font),
+            font_size: GAME_FONT_SIZE,
+            font_scale,
+            font_scale_aspect: 1.0,
+            rotation: 0.0,
+            color,
+        },
+    );
+}
+
+//phase, round timer and the limit of the mode at the top of the 3d view
+pub fn draw_match_hud(
+    status: &MatchStatus,
+    roster: &[Player],
+    player: &Player,
+    font: &Font,
+    layout: &Layout,
+    now: f64,
+) {
+    let remaining = status.remaining(now);
+    let text = match status.state.phase {
+        MatchPhase::Warmup if remaining > 0.0 => {
+            format!("Warmup, match starts in {}", format_time(remaining))
+        }
+        MatchPhase::Warmup => String::from("Warmup"),
+        MatchPhase::Intermission => return,
+        MatchPhase::InProgress => match status.options.mode {
+            GameMode::Deathmatch => return,
+            GameMode::TimedDeathmatch | GameMode::TeamDeathmatch => {
+                let mut parts = vec![];
+                if status.options.mode == GameMode::TeamDeathmatch {
+                    parts.push(team_totals(&with_local_player(roster, player)));
+                }
+                if status.options.time_limit > 0 {
+                    parts.push(format_time(remaining));
+                }
+                if status.options.frag_limit > 0 {
+                    parts.push(format!("Frag limit {}", status.options.frag_limit));
+                }
+                parts.join("    ")
+            }
+            GameMode::CaptureTheFlag => {
+                let mut parts = vec![team_totals(&with_local_player(roster, player))];
+                if let Some(team) = player.carrying_flag {
+                    parts.push(format!("You carry the {} flag", team.name()));
+                }
+                if status.options.time_limit > 0 {
+                    parts.push(format_time(remaining));
+                }
+                parts.join("    ")
+            }
+            GameMode::LastManStanding => {
+                let alive = roster
+                    .iter()
+                    .filter(|player| {
+                        !player.spectator && matches!(player.player_status, PlayerStatus::Active)
+                    })
+                    .count();
+                format!("Alive {}", alive)
+            }
+        },
+    };
+    if text.is_empty() {
+        return;
+    }
+    draw_centered(
+        text.as_str(),
+        layout.viewport.y + layout.line_height,
+        layout.scale * 1.5,
+        WHITE,
+        font,
+        layout,
+    );
+}
+
+//final scoreboard of the round with the winner and the time until the restart
+pub fn draw_winner_screen(
+    status: &MatchStatus,
+    roster: &[Player],
+    player: &Player,
+    font: &Font,
+    layout: &Layout,
+    now: f64,
+) {
+    draw_scoreboard(roster, player, font, layout);
+    let winner = status.state.winner.as_ref().map(|id| {
+        if *id == player.id {
+            String::from("You win")
+        } else {
+            let name = roster
+                .iter()
+                .find(|_player| _player.id == *id)
+                .map(|_player| _player.name.clone())
+                .unwrap_or_default();
+            format!("{} wins", name)
+        }
+    });
+    let bottom = layout.viewport.y + layout.viewport.h;
+    draw_centered(
+        winner.as_deref().unwrap_or("Round over"),
+        bottom - layout.line_height * 2.0,
+        layout.scale * 2.0,
+        YELLOW,
+        font,
+        layout,
+    );
+    let remaining = status.remaining(now);
+    if remaining > 0.0 {
+        draw_centered(
+            format!("Next round in {}", format_time(remaining)).as_str(),
+            bottom - layout.line_height,
+            layout.scale,
+            WHITE,
+            font,
+            layout,
+        );
+    }
+}
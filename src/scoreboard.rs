@@ -0,0 +1,141 @@
+use crate::layout::Layout;
+use crate::models::*;
+use crate::preferences::*;
+use macroquad::prelude::*;
+
+fn kill_death_ratio(player: &Player) -> f32 {
+    player.kills as f32 / u32::max(1, player.deaths) as f32
+}
+
+fn connection_status(player: &Player) -> &'static str {
+    if player.spectator {
+        return "spectating";
+    }
+    match player.player_status {
+        PlayerStatus::Active => "playing",
+        PlayerStatus::Killed => "dead",
+        PlayerStatus::Disconnent => "disconnected",
+        PlayerStatus::Unknown => "unknown",
+    }
+}
+
+//the local player is taken from this client, the copy from the server may be behind
+pub fn with_local_player<'a>(roster: &'a [Player], player: &'a Player) -> Vec<&'a Player> {
+    let mut players: Vec<&Player> = roster
+        .iter()
+        .filter(|_player| _player.id != player.id)
+        .collect();
+    players.push(player);
+    players
+}
+
+pub fn team_score(players: &[&Player], team: Team) -> u32 {
+    players
+        .iter()
+        .filter(|player| player.team == Some(team))
+        .map(|player| player.score)
+        .sum()
+}
+
+//"Red 12 : 9 Blue", empty when nobody plays in a team
+pub fn team_totals(players: &[&Player]) -> String {
+    if players.iter().all(|player| player.team.is_none()) {
+        return String::new();
+    }
+    format!(
+        "{} {} : {} {}",
+        Team::Red.name(),
+        team_score(players, Team::Red),
+        team_score(players, Team::Blue),
+        Team::Blue.name()
+    )
+}
+
+//every player sorted by kills, shown over the 3d view while the scoreboard key is held
+pub fn draw_scoreboard(roster: &[Player], player: &Player, font: &Font, layout: &Layout) {
+    let mut players = with_local_player(roster, player);
+    players.sort_by(|a, b| {
+        b.kills
+            .cmp(&a.kills)
+            .then(a.deaths.cmp(&b.deaths))
+            .then(a.name.cmp(&b.name))
+    });
+
+    let area = layout.viewport;
+    draw_rectangle(
+        area.x,
+        area.y,
+        area.w,
+        area.h,
+        Color::new(0.0, 0.0, 0.0, 0.75),
+    );
+    let params = TextParams {
+        font: Some(font),
+        font_size: GAME_FONT_SIZE,
+        font_scale: layout.scale,
+        font_scale_aspect: 1.0,
+        rotation: 0.0,
+        color: WHITE,
+    };
+    let line_height = layout.line_height * 0.7;
+    let left = area.x + 8.0 * layout.scale;
+    //name, kills, deaths, k/d, ping, status
+    let columns = [0.0, 0.34, 0.46, 0.58, 0.70, 0.82].map(|column| left + column * area.w);
+    let draw_row = |cells: [String; 6], top: f32, color: Color| {
+        for (cell, x) in cells.iter().zip(columns) {
+            draw_text_ex(
+                cell.as_str(),
+                x,
+                top,
+                TextParams {
+                    color,
+                    ..params.clone()
+                },
+            );
+        }
+    };
+
+    let mut top_offset = area.y + line_height * 1.5;
+    let totals = team_totals(&players);
+    if !totals.is_empty() {
+        draw_text_ex(totals.as_str(), left, top_offset, params.clone());
+        top_offset += line_height * 1.5;
+    }
+    draw_row(
+        ["Name", "Kills", "Deaths", "K/D", "Ping", "Status"].map(String::from),
+        top_offset,
+        GRAY,
+    );
+    for _player in players {
+        top_offset += line_height;
+        if top_offset > area.y + area.h {
+            break;
+        }
+        let is_local = _player.id == player.id;
+        if is_local {
+            draw_rectangle(
+                area.x,
+                top_offset - line_height * 0.75,
+                area.w,
+                line_height,
+                Color::new(1.0, 1.0, 1.0, 0.15),
+            );
+        }
+        draw_row(
+            [
+                _player.name.clone(),
+                format!("{}", _player.kills),
+                format!("{}", _player.deaths),
+                format!("{:.2}", kill_death_ratio(_player)),
+                format!("{}ms", _player.ping),
+                String::from(connection_status(_player)),
+            ],
+            top_offset,
+            match (is_local, _player.team) {
+                (true, _) => YELLOW,
+                (false, Some(team)) => team.color(),
+                (false, None) => WHITE,
+            },
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use crate::input::*;
+use crate::models::Status;
+use crate::preferences::*;
+use crate::utils::*;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(String, std::io::Error),
+    Parse(String, serde_json::Error),
+    Serialize(String, serde_json::Error),
+    Invalid(String, &'static str, String),
+}
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "{}: {}", path, e),
+            SettingsError::Parse(path, e) => write!(f, "{}: {}", path, e),
+            SettingsError::Serialize(path, e) => {
+                write!(f, "{}: could not write settings: {}", path, e)
+            }
+            SettingsError::Invalid(path, field, message) => {
+                write!(f, "{}: `{}` {}", path, field, message)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub move_speed: f32,
+    pub look_speed: f32,
+    pub invert_y: bool,
+    //vertical field of view in degrees
+    pub fov: f32,
+    //from 0 to MAX_VOLUME
+    pub volume: f32,
+    //no sound is played at all, for machines without an audio device
+    pub mute: bool,
+    pub server_address: String,
+    pub player_name: String,
+    pub default_map: String,
+    //most recent first
+    pub recent_servers: Vec<String>,
+    pub key_bindings: KeyBindings,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub path: String,
+    //the file at path failed to load, it is only overwritten when the player saves the options
+    #[serde(skip_serializing, skip_deserializing)]
+    pub load_error: Option<String>,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            move_speed: MOVE_SPEED,
+            look_speed: LOOK_SPEED,
+            invert_y: false,
+            fov: DEFAULT_FOV,
+            volume: MAX_VOLUME,
+            mute: false,
+            server_address: String::new(),
+            player_name: String::new(),
+            default_map: String::from(DEFAULT_MAP_PATH),
+            recent_servers: vec![],
+            key_bindings: KeyBindings::new(),
+            path: String::from(SETTINGS_PATH),
+            load_error: None,
+        }
+    }
+}
+impl Settings {
+    //missing file is not an error, defaults are used until settings are saved
+    pub fn load(path: &str) -> Result<Self, SettingsError> {
+        if !Path::new(path).exists() {
+            return Ok(Settings {
+                path: String::from(path),
+                ..Default::default()
+            });
+        }
+        let content = read_file(path).map_err(|e| SettingsError::Io(String::from(path), e))?;
+        let mut settings: Settings = serde_json::from_str(&content)
+            .map_err(|e| SettingsError::Parse(String::from(path), e))?;
+        settings.path = String::from(path);
+        settings.validate()?;
+        Ok(settings)
+    }
+    pub fn save(&self) -> Result<(), SettingsError> {
+        self.validate()?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| SettingsError::Serialize(self.path.clone(), e))?;
+        std::fs::write(&self.path, content).map_err(|e| SettingsError::Io(self.path.clone(), e))
+    }
+    //remember what was used to join a game, so the entry screens come pre-filled next time
+    pub fn remember_session(&mut self, server_address: &str, player_name: &str, map_path: &str) {
+        self.server_address = String::from(server_address);
+        self.player_name = String::from(player_name);
+        self.default_map = String::from(map_path);
+        self.recent_servers
+            .retain(|recent| recent.as_str() != server_address);
+        self.recent_servers.insert(0, String::from(server_address));
+        self.recent_servers.truncate(MAX_RECENT_SERVERS);
+    }
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let invalid = |field: &'static str, message: &str| {
+            Err(SettingsError::Invalid(
+                self.path.clone(),
+                field,
+                String::from(message),
+            ))
+        };
+        if !(self.move_speed > 0.0 && self.move_speed <= MAX_MOVE_SPEED) {
+            return invalid(
+                "move_speed",
+                &format!("must be above 0 and at most {}", MAX_MOVE_SPEED),
+            );
+        }
+        if !(self.look_speed > 0.0 && self.look_speed <= MAX_LOOK_SPEED) {
+            return invalid(
+                "look_speed",
+                &format!("must be above 0 and at most {}", MAX_LOOK_SPEED),
+            );
+        }
+        if !(MIN_FOV..=MAX_FOV).contains(&self.fov) {
+            return invalid(
+                "fov",
+                &format!("must be between {} and {} degrees", MIN_FOV, MAX_FOV),
+            );
+        }
+        if !(0.0..=MAX_VOLUME).contains(&self.volume) {
+            return invalid("volume", &format!("must be between 0 and {}", MAX_VOLUME));
+        }
+        if !self.server_address.chars().all(is_valid_address_char) {
+            return invalid("server_address", "contains invalid characters");
+        }
+        //host names are not resolved here, only the format is checked
+        if !self.server_address.is_empty() {
+            if let Err(e) = split_host_port(&self.server_address) {
+                return invalid("server_address", &e);
+            }
+        }
+        if self
+            .recent_servers
+            .iter()
+            .any(|recent| !recent.chars().all(is_valid_address_char))
+        {
+            return invalid("recent_servers", "contains invalid characters");
+        }
+        if self.player_name.len() > MAX_NAME_LENGTH {
+            return invalid(
+                "player_name",
+                &format!("must be at most {} characters long", MAX_NAME_LENGTH),
+            );
+        }
+        if !self.player_name.chars().all(is_valid_name_char) {
+            return invalid("player_name", "contains invalid characters");
+        }
+        if !self.default_map.is_empty() && !Path::new(&self.default_map).exists() {
+            return invalid("default_map", "file does not exist");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OptionsItem {
+    MoveSpeed,
+    LookSpeed,
+    InvertY,
+    Fov,
+    Volume,
+    Mute,
+    PlayerName,
+    ServerAddress,
+    DefaultMap,
+    KeyBindings,
+    Save,
+    Back,
+}
+const OPTIONS_ITEMS: [OptionsItem; 12] = [
+    OptionsItem::MoveSpeed,
+    OptionsItem::LookSpeed,
+    OptionsItem::InvertY,
+    OptionsItem::Fov,
+    OptionsItem::Volume,
+    OptionsItem::Mute,
+    OptionsItem::PlayerName,
+    OptionsItem::ServerAddress,
+    OptionsItem::DefaultMap,
+    OptionsItem::KeyBindings,
+    OptionsItem::Save,
+    OptionsItem::Back,
+];
+
+//options screen, edits a copy of the settings which replaces the original once saved
+pub struct OptionsMenu {
+    pub draft: Settings,
+    pub return_to: Status,
+    selected: usize,
+    rebind_menu: Option<RebindMenu>,
+    maps: Vec<String>,
+    error: Option<String>,
+}
+impl OptionsMenu {
+    pub fn new(settings: &Settings, return_to: Status) -> Self {
+        OptionsMenu {
+            draft: settings.clone(),
+            return_to,
+            selected: 0,
+            rebind_menu: None,
+            maps: list_maps(MAPS_DIRECTORY_PATH),
+            error: None,
+        }
+    }
+    //returns false once the menu has been closed
+    pub fn update(&mut self, settings: &mut Settings) -> bool {
+        if let Some(menu) = self.rebind_menu.as_mut() {
+            if !menu.update(&mut self.draft.key_bindings) {
+                self.rebind_menu = None;
+            }
+            return true;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            return false;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = usize::min(OPTIONS_ITEMS.len() - 1, self.selected + 1);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        let step = if is_key_pressed(KeyCode::Right) {
+            1.0
+        } else if is_key_pressed(KeyCode::Left) {
+            -1.0
+        } else {
+            0.0
+        };
+        let typed = get_char_pressed();
+        let enter = is_key_pressed(KeyCode::Enter);
+        let backspace = is_key_pressed(KeyCode::Backspace);
+        match OPTIONS_ITEMS[self.selected] {
+            OptionsItem::MoveSpeed => {
+                self.draft.move_speed =
+                    (self.draft.move_speed + step * 0.005).clamp(0.005, MAX_MOVE_SPEED);
+            }
+            OptionsItem::LookSpeed => {
+                self.draft.look_speed =
+                    (self.draft.look_speed + step * 0.05).clamp(0.05, MAX_LOOK_SPEED);
+            }
+            OptionsItem::InvertY => {
+                if step != 0.0 || enter {
+                    self.draft.invert_y = !self.draft.invert_y;
+                }
+            }
+            OptionsItem::Fov => {
+                self.draft.fov = (self.draft.fov + step * 5.0).clamp(MIN_FOV, MAX_FOV);
+            }
+            OptionsItem::Volume => {
+                self.draft.volume = (self.draft.volume + step * 0.1).clamp(0.0, MAX_VOLUME);
+            }
+            OptionsItem::Mute => {
+                if step != 0.0 || enter {
+                    self.draft.mute = !self.draft.mute;
+                }
+            }
+            OptionsItem::PlayerName => {
+                if let Some(c) = typed {
+                    if self.draft.player_name.len() < MAX_NAME_LENGTH && is_valid_name_char(c) {
+                        self.draft.player_name.push(c);
+                    }
+                }
+                if backspace {
+                    self.draft.player_name.pop();
+                }
+            }
+            OptionsItem::ServerAddress => {
+                if let Some(c) = typed {
+                    if is_valid_address_char(c) {
+                        self.draft.server_address.push(c);
+                    }
+                }
+                if backspace {
+                    self.draft.server_address.pop();
+                }
+            }
+            OptionsItem::DefaultMap => {
+                if step != 0.0 && !self.maps.is_empty() {
+                    let current = self
+                        .maps
+                        .iter()
+                        .position(|map| *map == self.draft.default_map);
+                    let next = match current {
+                        Some(index) if step > 0.0 => (index + 1) % self.maps.len(),
+                        Some(index) => (index + self.maps.len() - 1) % self.maps.len(),
+                        None => 0,
+                    };
+                    self.draft.default_map = self.maps[next].clone();
+                }
+            }
+            OptionsItem::KeyBindings => {
+                if enter {
+                    self.rebind_menu = Some(RebindMenu::default());
+                }
+            }
+            OptionsItem::Save => {
+                if enter {
+                    match self.draft.save() {
+                        Ok(_) => {
+                            *settings = self.draft.clone();
+                            settings.load_error = None;
+                            return false;
+                        }
+                        Err(e) => self.error = Some(format!("{}", e)),
+                    }
+                }
+            }
+            OptionsItem::Back => {
+                if enter {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+    pub fn draw(&self) {
+        if let Some(ref menu) = self.rebind_menu {
+            menu.draw(&self.draft.key_bindings);
+            return;
+        }
+        clear_background(BLACK);
+        draw_text(
+            "Options. Left/Right: change, Enter: select, Escape: back without saving",
+            10.0,
+            20.0,
+            CONSOLE_FONT_SIZE,
+            LIGHTGRAY,
+        );
+        let mut off_set_y = 60.0;
+        for (index, item) in OPTIONS_ITEMS.iter().enumerate() {
+            let text = match item {
+                OptionsItem::MoveSpeed => {
+                    format!("{:<20}{:.3}", "Move speed", self.draft.move_speed)
+                }
+                OptionsItem::LookSpeed => {
+                    format!("{:<20}{:.2}", "Look speed", self.draft.look_speed)
+                }
+                OptionsItem::InvertY => format!("{:<20}{}", "Invert Y", self.draft.invert_y),
+                OptionsItem::Fov => format!("{:<20}{}", "Field of view", self.draft.fov),
+                OptionsItem::Volume => format!("{:<20}{:.1}", "Volume", self.draft.volume),
+                OptionsItem::Mute => format!("{:<20}{}", "Mute", self.draft.mute),
+                OptionsItem::PlayerName => {
+                    format!("{:<20}{}", "Player name", self.draft.player_name)
+                }
+                OptionsItem::ServerAddress => {
+                    format!("{:<20}{}", "Server address", self.draft.server_address)
+                }
+                OptionsItem::DefaultMap => {
+                    format!("{:<20}{}", "Default map", self.draft.default_map)
+                }
+                OptionsItem::KeyBindings => String::from("Key bindings..."),
+                OptionsItem::Save => String::from("Save"),
+                OptionsItem::Back => String::from("Back"),
+            };
+            if index == self.selected {
+                draw_rectangle(
+                    0.0,
+                    off_set_y - 5.0 - 12.0,
+                    screen_width(),
+                    CONSOLE_FONT_SIZE + 5.0,
+                    LIGHTGRAY,
+                );
+                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, BLACK);
+            } else {
+                draw_text(text.as_str(), 10.0, off_set_y, CONSOLE_FONT_SIZE, LIGHTGRAY);
+            }
+            off_set_y += 30.0;
+        }
+        if let Some(ref error) = self.error {
+            draw_text(
+                error.as_str(),
+                10.0,
+                off_set_y + 10.0,
+                CONSOLE_FONT_SIZE,
+                RED,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_settings_are_the_defaults() {
+        let example = Settings::load("settings.example.json").unwrap();
+        assert_eq!(
+            example,
+            Settings {
+                path: String::from("settings.example.json"),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -1,6 +1,11 @@
-use macroquad::prelude::{vec3, Vec3};
+use crate::preferences::SOUND_ROLLOFF;
+use crate::preferences::{DEFAULT_SERVER_PORT, FLAG_BASE_BLUE, FLAG_BASE_RED};
+use macroquad::prelude::{vec2, vec3, Vec2, Vec3};
 use std::f64::consts::PI;
 use std::fs;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 //read content of the file into string
@@ -14,13 +19,13 @@ pub fn read_file(file_path: &str) -> Result<std::string::String, std::io::Error>
   - comtains minimum 3 lanes,
   - contains at least one empty cell
 */
-pub fn is_map_valid(content: &String) -> bool {
+pub fn is_map_valid(content: &str) -> bool {
     let mut len: usize = 0;
     let mut num_of_lines: u32 = 0;
     let mut num_of_empty: u32 = 0;
     for line in content.lines() {
         for ch in line.chars() {
-            if ch == ' ' {
+            if is_floor(ch) {
                 num_of_empty += 1;
             }
         }
@@ -43,27 +48,105 @@ pub fn is_map_valid(content: &String) -> bool {
     if num_of_empty == 0 {
         return false;
     }
+    //maps without flag bases are fine, otherwise each team needs exactly one
+    let red_bases = find_markers(content, FLAG_BASE_RED).len();
+    let blue_bases = find_markers(content, FLAG_BASE_BLUE).len();
+    if (red_bases, blue_bases) != (0, 0) && (red_bases, blue_bases) != (1, 1) {
+        return false;
+    }
 
     true
 }
 
 //converts srting conttents into vector of vectots whre "true" represents a wall and "false" represents empty cell
-pub fn map_to_slice(content: &String) -> Vec<Vec<bool>> {
+pub fn map_to_slice(content: &str) -> Vec<Vec<bool>> {
     let mut map = vec![];
     for line in content.lines() {
         let mut l = vec![];
         for ch in line.chars() {
-            if ch == ' ' {
-                l.push(false);
-            } else {
-                l.push(true);
-            }
+            l.push(!is_floor(ch));
         }
         map.push(l);
     }
     map
 }
 
+//flag bases are open cells like spaces
+fn is_floor(ch: char) -> bool {
+    ch == ' ' || ch == FLAG_BASE_RED || ch == FLAG_BASE_BLUE
+}
+
+//cells (x, z) of the map marked with the character
+pub fn find_markers(content: &str, marker: char) -> Vec<(usize, usize)> {
+    let mut cells = vec![];
+    for (z, line) in content.lines().enumerate() {
+        for (x, ch) in line.chars().enumerate() {
+            if ch == marker {
+                cells.push((x, z));
+            }
+        }
+    }
+    cells
+}
+
+//bits of the neighbour walls mask returned by neighbour_walls
+pub const WALL_UP: u8 = 1;
+pub const WALL_RIGHT: u8 = 2;
+pub const WALL_DOWN: u8 = 4;
+pub const WALL_LEFT: u8 = 8;
+
+//walls around the cell as a bitmask. Cells outside of the map count as walls
+pub fn neighbour_walls(map: &[Vec<bool>], x: i32, z: i32) -> u8 {
+    let is_wall = |x: i32, z: i32| -> bool {
+        if x < 0 || z < 0 {
+            return true;
+        }
+        match map.get(z as usize).and_then(|line| line.get(x as usize)) {
+            Some(cell) => *cell,
+            None => true,
+        }
+    };
+    let mut walls = 0;
+    if is_wall(x, z - 1) {
+        walls |= WALL_UP;
+    }
+    if is_wall(x + 1, z) {
+        walls |= WALL_RIGHT;
+    }
+    if is_wall(x, z + 1) {
+        walls |= WALL_DOWN;
+    }
+    if is_wall(x - 1, z) {
+        walls |= WALL_LEFT;
+    }
+    walls
+}
+
+/*
+    keeps a cell sized marker at position (x, z) from crossing into the walls around cell (index_x, index_z).
+    Every wall only blocks its own side, so tunnels, corners, t-junctions and dead ends
+    are all combinations of the same four clamps
+*/
+pub fn clamp_to_cell(position: Vec2, index_x: i32, index_z: i32, walls: u8) -> Vec2 {
+    let index_x = index_x as f32;
+    let index_z = index_z as f32;
+    let mut x = position.x;
+    let mut z = position.y;
+    if walls & WALL_UP != 0 {
+        z = f32::max(z, index_z);
+    }
+    if walls & WALL_RIGHT != 0 {
+        x = f32::min(x, index_x);
+    }
+    if walls & WALL_DOWN != 0 {
+        z = f32::min(z, index_z);
+    }
+    if walls & WALL_LEFT != 0 {
+        x = f32::max(x, index_x);
+    }
+    vec2(x, z)
+}
+
 pub fn generate_up_to(num: usize) -> usize {
     rand::random_range(0..num)
 }
@@ -83,18 +166,122 @@ pub fn orientaion_to_degrees(orientation: Vec3) -> f32 {
     theta
 }
 
-pub fn is_valid_ip_char(c: char) -> bool {
-    if c >= '0' && c <= '9' {
+//characters of ip addresses, [ipv6] literals and host names
+pub fn is_valid_address_char(c: char) -> bool {
+    if c.is_ascii_alphanumeric() {
         return true;
     }
-    if c == '.' || c == ':' {
+    if c == '.' || c == ':' || c == '-' || c == '[' || c == ']' {
         return true;
     }
     false
 }
 
+/*
+    splits server address into host and port. Accepts
+  - host:port and ipv4:port,
+  - [ipv6]:port,
+  - host, ipv4, [ipv6] and bare ipv6 without port
+*/
+pub fn split_host_port(address: &str) -> Result<(&str, Option<u16>), String> {
+    let address = address.trim();
+    if address.is_empty() {
+        return Err(String::from("address is empty"));
+    }
+    let parse_port = |port: &str| -> Result<Option<u16>, String> {
+        match port.parse::<u16>() {
+            Ok(port) => Ok(Some(port)),
+            Err(_) => Err(format!("invalid port `{}`", port)),
+        }
+    };
+    if let Some(rest) = address.strip_prefix('[') {
+        let Some((host, after)) = rest.split_once(']') else {
+            return Err(String::from("missing `]` after ipv6 address"));
+        };
+        return match after.strip_prefix(':') {
+            Some(port) => Ok((host, parse_port(port)?)),
+            None if after.is_empty() => Ok((host, None)),
+            None => Err(format!("unexpected `{}` after ipv6 address", after)),
+        };
+    }
+    match address.matches(':').count() {
+        0 => Ok((address, None)),
+        1 => {
+            let (host, port) = address.split_once(':').unwrap_or((address, ""));
+            if host.is_empty() {
+                return Err(String::from("host is missing"));
+            }
+            Ok((host, parse_port(port)?))
+        }
+        //ipv6 without brackets can not have a port
+        _ => Ok((address, None)),
+    }
+}
+
+//parses the address and resolves host names, DEFAULT_SERVER_PORT is used when port is omitted
+pub fn resolve_server_address(address: &str) -> Result<SocketAddr, String> {
+    let (host, port) = split_host_port(address)?;
+    let port = port.unwrap_or(DEFAULT_SERVER_PORT);
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+    match (host, port).to_socket_addrs() {
+        Ok(mut addresses) => addresses
+            .next()
+            .ok_or(format!("no address found for `{}`", host)),
+        Err(_) => Err(format!("could not resolve `{}`", host)),
+    }
+}
+
+//resolves the server address and binds a socket of the same address family
+pub fn connect(address: &str) -> Result<(SocketAddr, UdpSocket), String> {
+    let server = resolve_server_address(address)?;
+    let local = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    match UdpSocket::bind(local) {
+        Ok(socket) => Ok((server, socket)),
+        Err(e) => Err(format!("could not open socket: {}", e)),
+    }
+}
+
+type Connection = Result<(SocketAddr, UdpSocket), String>;
+
+//connect running in a thread, so a slow host name lookup does not freeze the window
+pub struct PendingConnection {
+    pub address: String,
+    result: Arc<Mutex<Option<Connection>>>,
+}
+impl PendingConnection {
+    pub fn start(address: &str) -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&result);
+        let target = String::from(address);
+        thread::spawn(move || {
+            let connection = connect(&target);
+            match shared.lock() {
+                Ok(mut shared) => *shared = Some(connection),
+                Err(e) => println!("Error while locking connection: {:?}", e),
+            }
+        });
+        PendingConnection {
+            address: String::from(address),
+            result,
+        }
+    }
+    //None while the lookup is still running
+    pub fn take(&self) -> Option<Connection> {
+        match self.result.lock() {
+            Ok(mut result) => result.take(),
+            Err(e) => Some(Err(format!("lookup failed: {:?}", e))),
+        }
+    }
+}
+
 pub fn is_valid_name_char(c: char) -> bool {
-    c >= ' ' && c <= '~'
+    (' '..='~').contains(&c)
 }
 
 pub fn get_ms() -> Option<u128> {
@@ -104,27 +291,283 @@ pub fn get_ms() -> Option<u128> {
     None
 }
 
-pub fn get_settings(move_speed: &mut f32, look_speed: &mut f32) {
-    let content = read_file("settings");
-    if let Ok(_content) = content {
-        let lines = _content.lines();
-        for line in lines {
-            let parts: Vec<&str> = line.split(":").collect();
-            if parts.len() == 2 {
-                let key = parts[0].trim();
-                let value = parts[1].trim();
-
-                if String::from(key) == String::from("MOVE_SPEED") {
-                    if let Ok(_value) = value.parse::<f32>() {
-                        *move_speed = _value;
-                    }
-                }
-                if String::from(key) == String::from("LOOK_SPEED") {
-                    if let Ok(_value) = value.parse::<f32>() {
-                        *look_speed = _value;
-                    }
-                }
+//paths of the map files in the directory, hidden files are skipped
+pub fn list_maps(directory: &str) -> Vec<String> {
+    let mut map_paths = vec![];
+    if let Ok(paths) = fs::read_dir(directory) {
+        for _path in paths.flatten() {
+            let path_as_str = format!("{}", _path.path().display());
+            if !path_as_str.contains("/.") {
+                map_paths.push(path_as_str);
             }
         }
     }
+    map_paths.sort();
+    map_paths
+}
+
+/*
+    16 bit stereo wav of mono samples, pan from -1 (left) to 1 (right).
+    Equal power panning keeps the loudness the same in every position
+*/
+pub fn stereo_wav(samples: &[f32], sample_rate: u32, pan: f32) -> Vec<u8> {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+    let gains = [angle.cos(), angle.sin()];
+    let data_size = samples.len() as u32 * 4;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    //pcm, two channels
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 4).to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        for gain in gains {
+            let value = (sample * gain).clamp(-1.0, 1.0) * i16::MAX as f32;
+            bytes.extend_from_slice(&(value as i16).to_le_bytes());
+        }
+    }
+    bytes
+}
+
+//volume from 0 to 1 and pan from -1 (left) to 1 (right) of a sound at source heard at listener
+pub fn sound_volume_and_pan(listener: Vec3, right: Vec3, source: Vec3) -> (f32, f32) {
+    let offset = source - listener;
+    let distance = offset.length();
+    let volume = 1.0 / (1.0 + distance * SOUND_ROLLOFF);
+    let pan = if distance > 0.001 {
+        offset.normalize().dot(right.normalize_or_zero())
+    } else {
+        0.0
+    };
+    (volume, pan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(rows: &[&str]) -> Vec<Vec<bool>> {
+        map_to_slice(&rows.join("\n"))
+    }
+
+    #[test]
+    fn neighbour_walls_of_open_crossing() {
+        let map = map(&["W W", "   ", "W W"]);
+        assert_eq!(neighbour_walls(&map, 1, 1), 0);
+    }
+
+    #[test]
+    fn neighbour_walls_of_tunnels() {
+        let map = map(&["WWWWW", "W   W", "WWW W", "WWW W", "WWWWW"]);
+        assert_eq!(neighbour_walls(&map, 2, 1), WALL_UP | WALL_DOWN);
+        assert_eq!(neighbour_walls(&map, 3, 2), WALL_LEFT | WALL_RIGHT);
+    }
+
+    #[test]
+    fn neighbour_walls_of_corners() {
+        let map = map(&["WWWW", "W  W", "W  W", "WWWW"]);
+        assert_eq!(neighbour_walls(&map, 1, 1), WALL_UP | WALL_LEFT);
+        assert_eq!(neighbour_walls(&map, 2, 1), WALL_UP | WALL_RIGHT);
+        assert_eq!(neighbour_walls(&map, 2, 2), WALL_DOWN | WALL_RIGHT);
+        assert_eq!(neighbour_walls(&map, 1, 2), WALL_DOWN | WALL_LEFT);
+    }
+
+    #[test]
+    fn neighbour_walls_of_t_junctions() {
+        let map = map(&["WWWWW", "W   W", "W   W", "W   W", "WWWWW"]);
+        assert_eq!(neighbour_walls(&map, 2, 1), WALL_UP);
+        assert_eq!(neighbour_walls(&map, 3, 2), WALL_RIGHT);
+        assert_eq!(neighbour_walls(&map, 2, 3), WALL_DOWN);
+        assert_eq!(neighbour_walls(&map, 1, 2), WALL_LEFT);
+    }
+
+    #[test]
+    fn neighbour_walls_of_dead_end() {
+        let map = map(&["WWW", "W W", "W W"]);
+        assert_eq!(
+            neighbour_walls(&map, 1, 1),
+            WALL_UP | WALL_RIGHT | WALL_LEFT
+        );
+    }
+
+    #[test]
+    fn neighbour_walls_outside_of_map_count_as_walls() {
+        let map = map(&["   ", "   ", "   "]);
+        assert_eq!(neighbour_walls(&map, 0, 0), WALL_UP | WALL_LEFT);
+        assert_eq!(neighbour_walls(&map, 2, 2), WALL_DOWN | WALL_RIGHT);
+        assert_eq!(neighbour_walls(&map, 1, 0), WALL_UP);
+        assert_eq!(
+            neighbour_walls(&map, -2, 1),
+            WALL_UP | WALL_RIGHT | WALL_DOWN | WALL_LEFT
+        );
+        assert_eq!(
+            neighbour_walls(&map, 7, 7),
+            WALL_UP | WALL_RIGHT | WALL_DOWN | WALL_LEFT
+        );
+    }
+
+    #[test]
+    fn clamp_in_open_cell_keeps_position() {
+        let position = vec2(2.3, 1.8);
+        assert_eq!(clamp_to_cell(position, 2, 2, 0), position);
+    }
+
+    #[test]
+    fn clamp_in_horizontal_tunnel() {
+        let walls = WALL_UP | WALL_DOWN;
+        assert_eq!(clamp_to_cell(vec2(2.3, 1.8), 2, 2, walls), vec2(2.3, 2.0));
+        assert_eq!(clamp_to_cell(vec2(1.7, 2.2), 2, 2, walls), vec2(1.7, 2.0));
+    }
+
+    #[test]
+    fn clamp_in_vertical_tunnel() {
+        let walls = WALL_LEFT | WALL_RIGHT;
+        assert_eq!(clamp_to_cell(vec2(2.3, 1.8), 2, 2, walls), vec2(2.0, 1.8));
+        assert_eq!(clamp_to_cell(vec2(1.7, 2.2), 2, 2, walls), vec2(2.0, 2.2));
+    }
+
+    #[test]
+    fn clamp_in_corners() {
+        let position = vec2(1.7, 1.7);
+        assert_eq!(
+            clamp_to_cell(position, 2, 2, WALL_UP | WALL_LEFT),
+            vec2(2.0, 2.0)
+        );
+        let position = vec2(2.3, 1.7);
+        assert_eq!(
+            clamp_to_cell(position, 2, 2, WALL_UP | WALL_RIGHT),
+            vec2(2.0, 2.0)
+        );
+        let position = vec2(2.3, 2.3);
+        assert_eq!(
+            clamp_to_cell(position, 2, 2, WALL_DOWN | WALL_RIGHT),
+            vec2(2.0, 2.0)
+        );
+        let position = vec2(1.7, 2.3);
+        assert_eq!(
+            clamp_to_cell(position, 2, 2, WALL_DOWN | WALL_LEFT),
+            vec2(2.0, 2.0)
+        );
+        //moving away from the corner is not clamped
+        let position = vec2(2.3, 2.3);
+        assert_eq!(clamp_to_cell(position, 2, 2, WALL_UP | WALL_LEFT), position);
+    }
+
+    #[test]
+    fn clamp_in_t_junctions() {
+        let position = vec2(2.3, 1.7);
+        assert_eq!(clamp_to_cell(position, 2, 2, WALL_UP), vec2(2.3, 2.0));
+        let position = vec2(2.3, 2.3);
+        assert_eq!(clamp_to_cell(position, 2, 2, WALL_RIGHT), vec2(2.0, 2.3));
+        let position = vec2(1.7, 2.3);
+        assert_eq!(clamp_to_cell(position, 2, 2, WALL_DOWN), vec2(1.7, 2.0));
+        let position = vec2(1.7, 1.7);
+        assert_eq!(clamp_to_cell(position, 2, 2, WALL_LEFT), vec2(2.0, 1.7));
+    }
+
+    #[test]
+    fn clamp_in_dead_end() {
+        let walls = WALL_UP | WALL_RIGHT | WALL_LEFT;
+        assert_eq!(clamp_to_cell(vec2(2.3, 1.7), 2, 2, walls), vec2(2.0, 2.0));
+        assert_eq!(clamp_to_cell(vec2(1.7, 2.3), 2, 2, walls), vec2(2.0, 2.3));
+    }
+
+    #[test]
+    fn clamp_on_map_border() {
+        let map = map(&["   ", "   ", "   "]);
+        let walls = neighbour_walls(&map, 0, 0);
+        assert_eq!(clamp_to_cell(vec2(-0.3, -0.2), 0, 0, walls), vec2(0.0, 0.0));
+        let walls = neighbour_walls(&map, 2, 2);
+        assert_eq!(clamp_to_cell(vec2(2.4, 2.1), 2, 2, walls), vec2(2.0, 2.0));
+    }
+
+    #[test]
+    fn flag_bases_are_floor() {
+        let content = "WWWWW\nWR BW\nWWWWW";
+        assert!(is_map_valid(content));
+        assert_eq!(
+            map_to_slice(content)[1],
+            vec![true, false, false, false, true]
+        );
+        assert_eq!(find_markers(content, FLAG_BASE_RED), vec![(1, 1)]);
+        assert_eq!(find_markers(content, FLAG_BASE_BLUE), vec![(3, 1)]);
+    }
+
+    #[test]
+    fn flag_bases_come_in_pairs() {
+        assert!(!is_map_valid("WWWWW\nWR  W\nWWWWW"));
+        assert!(!is_map_valid("WWWWW\nWRRBW\nWWWWW"));
+        assert!(is_map_valid("WWWWW\nW   W\nWWWWW"));
+    }
+
+    #[test]
+    fn split_address_with_port() {
+        assert_eq!(
+            split_host_port("127.0.0.1:4000"),
+            Ok(("127.0.0.1", Some(4000)))
+        );
+        assert_eq!(
+            split_host_port("localhost:4000"),
+            Ok(("localhost", Some(4000)))
+        );
+        assert_eq!(split_host_port("[::1]:4000"), Ok(("::1", Some(4000))));
+    }
+
+    #[test]
+    fn split_address_without_port() {
+        assert_eq!(split_host_port("127.0.0.1"), Ok(("127.0.0.1", None)));
+        assert_eq!(split_host_port("localhost"), Ok(("localhost", None)));
+        assert_eq!(split_host_port("[::1]"), Ok(("::1", None)));
+        assert_eq!(split_host_port("fe80::1"), Ok(("fe80::1", None)));
+    }
+
+    #[test]
+    fn split_invalid_address() {
+        assert!(split_host_port("").is_err());
+        assert!(split_host_port(":4000").is_err());
+        assert!(split_host_port("localhost:port").is_err());
+        assert!(split_host_port("localhost:70000").is_err());
+        assert!(split_host_port("[::1").is_err());
+        assert!(split_host_port("[::1]4000").is_err());
+    }
+
+    #[test]
+    fn resolve_uses_default_port() {
+        let address = resolve_server_address("127.0.0.1").unwrap();
+        assert_eq!(address.port(), DEFAULT_SERVER_PORT);
+        let address = resolve_server_address("[::1]:4001").unwrap();
+        assert!(address.is_ipv6());
+        assert_eq!(address.port(), 4001);
+    }
+
+    #[test]
+    fn stereo_wav_has_header_and_both_channels() {
+        let bytes = stereo_wav(&[0.5, -0.5, 0.25], 22050, 0.0);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WAVE");
+        assert_eq!(bytes.len(), 44 + 3 * 4);
+        let left = i16::from_le_bytes([bytes[44], bytes[45]]);
+        let right = i16::from_le_bytes([bytes[46], bytes[47]]);
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn sounds_pan_to_their_side_and_fade_with_distance() {
+        let listener = vec3(0.0, 1.0, 0.0);
+        let right = vec3(0.0, 0.0, 1.0);
+        let (near, pan_right) = sound_volume_and_pan(listener, right, vec3(0.0, 1.0, 1.0));
+        let (far, pan_left) = sound_volume_and_pan(listener, right, vec3(0.0, 1.0, -5.0));
+        assert!(pan_right > 0.9);
+        assert!(pan_left < -0.9);
+        assert!(near > far);
+        assert_eq!(sound_volume_and_pan(listener, right, listener), (1.0, 0.0));
+    }
 }
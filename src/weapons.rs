@@ -0,0 +1,286 @@
+use crate::layout::Layout;
+use crate::preferences::*;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponKind {
+    Rifle,
+    Shotgun,
+    Launcher,
+}
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 3] = [WeaponKind::Rifle, WeaponKind::Shotgun, WeaponKind::Launcher];
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponKind::Rifle => "Rifle",
+            WeaponKind::Shotgun => "Shotgun",
+            WeaponKind::Launcher => "Launcher",
+        }
+    }
+    pub fn spec(&self) -> WeaponSpec {
+        match self {
+            WeaponKind::Rifle => WeaponSpec {
+                fire_interval: 0.15,
+                magazine: 30,
+                reload_time: 1.5,
+                rays: 1,
+                spread: 0.005,
+                range: MAX_SHOT_RANGE,
+                damage_factor: 1.0,
+                color: YELLOW,
+                projectile: None,
+            },
+            WeaponKind::Shotgun => WeaponSpec {
+                fire_interval: 0.9,
+                magazine: 6,
+                reload_time: 2.5,
+                rays: 8,
+                spread: 0.08,
+                range: 8.0,
+                damage_factor: 0.3,
+                color: ORANGE,
+                projectile: None,
+            },
+            WeaponKind::Launcher => WeaponSpec {
+                fire_interval: 1.2,
+                magazine: 4,
+                reload_time: 3.0,
+                rays: 1,
+                spread: 0.0,
+                range: MAX_SHOT_RANGE,
+                damage_factor: 2.0,
+                color: SKYBLUE,
+                projectile: Some(ProjectileSpec {
+                    speed: 6.0,
+                    lifetime: 6.0,
+                }),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WeaponSpec {
+    //seconds between two shots
+    pub fire_interval: f64,
+    pub magazine: u32,
+    pub reload_time: f64,
+    //pellets fired with one shot
+    pub rays: u32,
+    //largest deviation of a ray from the aim, in radians
+    pub spread: f32,
+    pub range: f32,
+    //of the shot damage set by the server, per ray
+    pub damage_factor: f32,
+    pub color: Color,
+    //weapons without projectiles hit instantly
+    pub projectile: Option<ProjectileSpec>,
+}
+impl WeaponSpec {
+    pub fn damage(&self, shot_damage: u32) -> u32 {
+        u32::max(1, (shot_damage as f32 * self.damage_factor).round() as u32)
+    }
+    //one direction per ray, spread around the aim
+    pub fn directions(&self, front: Vec3, right: Vec3) -> Vec<Vec3> {
+        let up = right.cross(front).normalize();
+        (0..self.rays)
+            .map(|_| {
+                let horizontal = rand::gen_range(-self.spread, self.spread);
+                let vertical = rand::gen_range(-self.spread, self.spread);
+                (front + right * horizontal + up * vertical).normalize()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectileSpec {
+    //units per second
+    pub speed: f32,
+    //seconds until a projectile that hit nothing is gone
+    pub lifetime: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    pub ammo: u32,
+    //seconds since the epoch when the running reload is done
+    reloaded_at: Option<f64>,
+    next_shot_at: f64,
+}
+impl Weapon {
+    fn new(kind: WeaponKind) -> Self {
+        Weapon {
+            kind,
+            ammo: kind.spec().magazine,
+            reloaded_at: None,
+            next_shot_at: 0.0,
+        }
+    }
+    pub fn is_reloading(&self) -> bool {
+        self.reloaded_at.is_some()
+    }
+}
+
+//weapons of the local player, every weapon keeps its own magazine
+#[derive(Debug, Clone)]
+pub struct Weapons {
+    weapons: Vec<Weapon>,
+    selected: usize,
+}
+impl Default for Weapons {
+    fn default() -> Self {
+        Weapons {
+            weapons: WeaponKind::ALL.into_iter().map(Weapon::new).collect(),
+            selected: 0,
+        }
+    }
+}
+impl Weapons {
+    pub fn selected(&self) -> &Weapon {
+        &self.weapons[self.selected]
+    }
+    //switching cancels a running reload
+    pub fn select(&mut self, index: usize, now: f64) {
+        if index == self.selected || index >= self.weapons.len() {
+            return;
+        }
+        self.weapons[self.selected].reloaded_at = None;
+        self.selected = index;
+        let weapon = &mut self.weapons[self.selected];
+        weapon.next_shot_at = f64::max(weapon.next_shot_at, now + WEAPON_SWITCH_TIME);
+    }
+    pub fn select_next(&mut self, now: f64) {
+        self.select((self.selected + 1) % self.weapons.len(), now);
+    }
+    pub fn reload(&mut self, now: f64) {
+        let weapon = &mut self.weapons[self.selected];
+        let spec = weapon.kind.spec();
+        if weapon.ammo < spec.magazine && !weapon.is_reloading() {
+            weapon.reloaded_at = Some(now + spec.reload_time);
+        }
+    }
+    pub fn update(&mut self, now: f64) {
+        let weapon = &mut self.weapons[self.selected];
+        if weapon.reloaded_at.is_some_and(|time| now >= time) {
+            weapon.reloaded_at = None;
+            weapon.ammo = weapon.kind.spec().magazine;
+        }
+    }
+    //returns true when the selected weapon fires, an empty magazine starts the reload
+    pub fn fire(&mut self, now: f64) -> bool {
+        self.update(now);
+        let weapon = &mut self.weapons[self.selected];
+        if weapon.is_reloading() || now < weapon.next_shot_at {
+            return false;
+        }
+        if weapon.ammo == 0 {
+            self.reload(now);
+            return false;
+        }
+        weapon.ammo -= 1;
+        weapon.next_shot_at = now + weapon.kind.spec().fire_interval;
+        if weapon.ammo == 0 {
+            self.reload(now);
+        }
+        true
+    }
+    //weapon name and ammo at the bottom right of the 3d view
+    pub fn draw(&self, font: &Font, layout: &Layout) {
+        let weapon = self.selected();
+        let text = if weapon.is_reloading() {
+            format!("{}  reloading", weapon.kind.name())
+        } else {
+            format!(
+                "{}  {}/{}",
+                weapon.kind.name(),
+                weapon.ammo,
+                weapon.kind.spec().magazine
+            )
+        };
+        let size = measure_text(text.as_str(), Some(font), GAME_FONT_SIZE, layout.scale);
+        draw_text_ex(
+            text.as_str(),
+            layout.viewport.x + layout.viewport.w - size.width - 6.0 * layout.scale,
+            layout.viewport.y + layout.viewport.h - 6.0 * layout.scale,
+            TextParams {
+                font: Some(font),
+                font_size: GAME_FONT_SIZE,
+                font_scale: layout.scale,
+                font_scale_aspect: 1.0,
+                rotation: 0.0,
+                color: weapon.kind.spec().color,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fire_interval_limits_the_rate_of_fire() {
+        let mut weapons = Weapons::default();
+        let interval = WeaponKind::Rifle.spec().fire_interval;
+        assert!(weapons.fire(10.0));
+        assert!(!weapons.fire(10.0 + interval / 2.0));
+        assert!(weapons.fire(10.0 + interval));
+        assert_eq!(
+            weapons.selected().ammo,
+            WeaponKind::Rifle.spec().magazine - 2
+        );
+    }
+
+    #[test]
+    fn empty_magazine_reloads_by_itself() {
+        let mut weapons = Weapons::default();
+        weapons.select(2, 0.0);
+        let spec = WeaponKind::Launcher.spec();
+        let mut now = WEAPON_SWITCH_TIME;
+        for _ in 0..spec.magazine {
+            assert!(weapons.fire(now));
+            now += spec.fire_interval;
+        }
+        assert_eq!(weapons.selected().ammo, 0);
+        assert!(weapons.selected().is_reloading());
+        assert!(!weapons.fire(now));
+
+        let reloaded = now - spec.fire_interval + spec.reload_time;
+        weapons.update(reloaded);
+        assert!(!weapons.selected().is_reloading());
+        assert_eq!(weapons.selected().ammo, spec.magazine);
+        assert!(weapons.fire(reloaded));
+    }
+
+    #[test]
+    fn switching_takes_time_and_cancels_the_reload() {
+        let mut weapons = Weapons::default();
+        assert!(weapons.fire(0.0));
+        weapons.reload(1.0);
+        assert!(weapons.selected().is_reloading());
+
+        weapons.select_next(1.0);
+        assert_eq!(weapons.selected().kind, WeaponKind::Shotgun);
+        assert!(!weapons.fire(1.0 + WEAPON_SWITCH_TIME / 2.0));
+        assert!(weapons.fire(1.0 + WEAPON_SWITCH_TIME));
+
+        //the rifle comes back with the magazine it was left with
+        weapons.select(0, 2.0);
+        assert!(!weapons.selected().is_reloading());
+        assert_eq!(
+            weapons.selected().ammo,
+            WeaponKind::Rifle.spec().magazine - 1
+        );
+    }
+
+    #[test]
+    fn full_magazine_is_not_reloaded() {
+        let mut weapons = Weapons::default();
+        weapons.reload(0.0);
+        assert!(!weapons.selected().is_reloading());
+    }
+}